@@ -0,0 +1,322 @@
+use crate::bench::BenchReport;
+use crate::{LoadOptions, load_scarb_metadata};
+use anyhow::Context;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::thread::available_parallelism;
+use std::time::Duration;
+
+/// Baseline median times of diagnostics calculation, stored in a JSON file.
+///
+/// Medians are keyed by the project, the machine fingerprint and the thread limit, in that order,
+/// so a single file can hold baselines of many projects measured on many machines. Projects are
+/// identified with [`baseline_project_key`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Baselines(BTreeMap<String, BTreeMap<String, BTreeMap<usize, Baseline>>>);
+
+/// Baseline of a single thread limit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Median cold diagnostics calculation time in milliseconds.
+    pub median_ms: f64,
+
+    /// Number of runs the median was calculated from.
+    pub runs: usize,
+}
+
+impl Baselines {
+    /// Reads baselines from `path`, or returns empty ones if the file does not exist.
+    pub fn read_or_default(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::read(path)
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read baselines: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse baselines: {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write baselines: {}", path.display()))
+    }
+
+    /// Stores cold medians from `report` as baselines of `project` on the current machine,
+    /// replacing the previous ones for the same thread limits.
+    pub fn update(&mut self, project: &str, report: &BenchReport) {
+        let baselines = self
+            .0
+            .entry(project.to_string())
+            .or_default()
+            .entry(machine_fingerprint())
+            .or_default();
+
+        for stats in &report.runs {
+            baselines.insert(
+                stats.threads_limit.get(),
+                Baseline {
+                    median_ms: stats.cold.median().as_secs_f64() * 1000.,
+                    runs: stats.cold.len(),
+                },
+            );
+        }
+    }
+
+    /// Compares cold medians from `report` with baselines of `project` on the current machine.
+    ///
+    /// A thread limit regresses if its median is slower than the baseline by more than
+    /// `threshold_percent` percent. Fails if any thread limit from `report` has no baseline, as
+    /// a comparison with nothing would silently pass.
+    pub fn compare(
+        &self,
+        project: &str,
+        report: &BenchReport,
+        threshold_percent: f64,
+    ) -> anyhow::Result<BaselineComparison> {
+        let machine = machine_fingerprint();
+        let baselines = self
+            .0
+            .get(project)
+            .with_context(|| format!("no baselines for project: {project}"))?
+            .get(&machine)
+            .with_context(|| format!("no baselines for project {project} on machine: {machine}"))?;
+
+        let rows = report
+            .runs
+            .iter()
+            .map(|stats| {
+                let threads_limit = stats.threads_limit.get();
+                let baseline = baselines.get(&threads_limit).with_context(|| {
+                    format!(
+                        "no baseline for project {project} on machine {machine} with thread \
+                         limit: {threads_limit}"
+                    )
+                })?;
+
+                Ok(BaselineComparisonRow {
+                    threads_limit,
+                    baseline: Duration::from_secs_f64(baseline.median_ms / 1000.),
+                    current: stats.cold.median(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(BaselineComparison {
+            rows,
+            threshold_percent,
+        })
+    }
+}
+
+/// Result of comparing a bench report with stored baselines.
+#[derive(Debug, Clone)]
+pub struct BaselineComparison {
+    pub rows: Vec<BaselineComparisonRow>,
+    pub threshold_percent: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BaselineComparisonRow {
+    pub threads_limit: usize,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+impl BaselineComparisonRow {
+    /// Returns the relative change of the median time in percent, positive when slower.
+    pub fn change_percent(&self) -> f64 {
+        (self.current.as_secs_f64() / self.baseline.as_secs_f64() - 1.) * 100.
+    }
+}
+
+impl BaselineComparison {
+    /// Returns rows with the median time regressed beyond the threshold.
+    pub fn regressions(&self) -> Vec<&BaselineComparisonRow> {
+        self.rows
+            .iter()
+            .filter(|row| self.is_regression(row))
+            .collect()
+    }
+
+    fn is_regression(&self, row: &BaselineComparisonRow) -> bool {
+        row.change_percent() > self.threshold_percent
+    }
+}
+
+impl fmt::Display for BaselineComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Machine: {}", machine_fingerprint())?;
+        writeln!(
+            f,
+            "{:>8} {:>12} {:>12} {:>10}",
+            "limit", "baseline", "median", "change"
+        )?;
+        for row in &self.rows {
+            let baseline = format!("{:.2?}", row.baseline);
+            let change = format!("{:+.1}%", row.change_percent());
+            let marker = if self.is_regression(row) {
+                " REGRESSION"
+            } else {
+                ""
+            };
+
+            writeln!(
+                f,
+                "{:>8} {:>12} {:>12.2?} {:>10}{marker}",
+                row.threads_limit, baseline, row.current, change
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the key identifying the Scarb project under `manifest_path` in [`Baselines`]: names of
+/// its workspace member packages.
+///
+/// Unlike the manifest path, the key is the same in every checkout of the project, so baselines
+/// can be shared between machines.
+pub fn baseline_project_key(manifest_path: &Path, options: &LoadOptions) -> anyhow::Result<String> {
+    let metadata = load_scarb_metadata(manifest_path, options)?;
+
+    Ok(metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace.members.contains(&package.id))
+        .map(|package| package.name.as_str())
+        .sorted()
+        .join(","))
+}
+
+/// Returns a description of the current machine, identifying it among machines baselines were
+/// measured on.
+///
+/// Consists of the OS, the architecture, the available parallelism and the CPU model, if it can be
+/// read, which is only the case on Linux.
+pub fn machine_fingerprint() -> String {
+    let parallelism = available_parallelism().map_or(1, |parallelism| parallelism.get());
+    let mut fingerprint = format!(
+        "{}-{}-{parallelism}cpu",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    if let Some(cpu_model) = cpu_model() {
+        fingerprint.push('-');
+        fingerprint.push_str(&cpu_model);
+    }
+    fingerprint
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    let model = cpuinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("model name"))?
+        .trim_start()
+        .strip_prefix(':')?
+        .trim();
+
+    Some(model.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Baseline, Baselines, machine_fingerprint};
+    use crate::bench::{BenchReport, Samples, ThreadCountStats};
+    use std::num::NonZero;
+    use std::time::Duration;
+
+    /// Creates a report with a single cold sample of the given milliseconds for each thread limit.
+    fn report(medians_ms: &[(usize, u64)]) -> BenchReport {
+        let runs = medians_ms
+            .iter()
+            .map(|&(threads_limit, median_ms)| {
+                let threads_limit = NonZero::new(threads_limit).unwrap();
+                let mut cold = Samples::default();
+                cold.push(Duration::from_millis(median_ms));
+                ThreadCountStats {
+                    threads_limit,
+                    threads: threads_limit,
+                    cold,
+                    warm: None,
+                    revalidation: None,
+                }
+            })
+            .collect();
+
+        BenchReport { runs }
+    }
+
+    #[test]
+    fn update_replaces_baselines_of_current_machine_only() {
+        let other_machine = Baseline {
+            median_ms: 1000.,
+            runs: 5,
+        };
+        let mut baselines = Baselines::default();
+        baselines
+            .0
+            .entry("hello".to_string())
+            .or_default()
+            .insert("other-machine".to_string(), [(1, other_machine)].into());
+
+        baselines.update("hello", &report(&[(1, 100), (2, 60)]));
+        baselines.update("hello", &report(&[(2, 50)]));
+
+        let project = &baselines.0["hello"];
+        assert_eq!(project["other-machine"][&1].median_ms, 1000.);
+
+        let current = &project[&machine_fingerprint()];
+        assert_eq!(current[&1].median_ms, 100.);
+        assert_eq!(current[&2].median_ms, 50.);
+        assert_eq!(current[&2].runs, 1);
+    }
+
+    #[test]
+    fn compare_reports_regressions_beyond_threshold() {
+        let mut baselines = Baselines::default();
+        baselines.update("hello", &report(&[(1, 100), (2, 100), (4, 100)]));
+
+        let comparison = baselines
+            .compare("hello", &report(&[(1, 109), (2, 111), (4, 80)]), 10.)
+            .unwrap();
+
+        assert_eq!(comparison.rows.len(), 3);
+        let regressions: Vec<_> = comparison
+            .regressions()
+            .iter()
+            .map(|row| row.threads_limit)
+            .collect();
+        assert_eq!(regressions, [2]);
+    }
+
+    #[test]
+    fn compare_fails_without_baseline() {
+        let mut baselines = Baselines::default();
+        assert!(
+            baselines
+                .compare("hello", &report(&[(1, 100)]), 10.)
+                .is_err()
+        );
+
+        baselines.update("hello", &report(&[(1, 100)]));
+        assert!(
+            baselines
+                .compare("world", &report(&[(1, 100)]), 10.)
+                .is_err()
+        );
+
+        let error = baselines
+            .compare("hello", &report(&[(1, 100), (2, 100)]), 10.)
+            .unwrap_err();
+        assert!(error.to_string().contains("thread limit: 2"));
+    }
+}
@@ -0,0 +1,296 @@
+use crate::diagnostics::DiagnosticController;
+use crate::memory::{format_peak_rss, peak_rss, reset_peak_rss};
+use crate::{LoadOptions, load_scarb_project_with_options};
+use anyhow::ensure;
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_defs::db::DefsGroup;
+use cairo_lang_defs::ids::ModuleId;
+use cairo_lang_filesystem::db::{FilesGroup, FilesGroupEx};
+use std::fmt::Write;
+use std::num::NonZero;
+use std::path::Path;
+use std::time::Duration;
+
+/// Results of running diagnostics calculation repeatedly for a sweep of thread counts.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// Statistics for each thread count, in the order of the sweep.
+    pub runs: Vec<ThreadCountStats>,
+}
+
+/// Timings of diagnostics calculation for a single thread count.
+#[derive(Debug, Clone)]
+pub struct ThreadCountStats {
+    /// Thread limit requested for the run.
+    pub threads_limit: NonZero<usize>,
+
+    /// Number of threads actually spawned by the pool,
+    /// which is capped by the available parallelism.
+    pub threads: NonZero<usize>,
+
+    /// Durations of calculations on a freshly loaded db.
+    pub cold: Samples,
+
+    /// Durations of repeated calculations on the same db, without any input changes.
+    ///
+    /// Present only if cache states were benchmarked.
+    pub warm: Option<Samples>,
+
+    /// Durations of repeated calculations on the same db after a no-op input change,
+    /// which forces salsa to revalidate memoized queries.
+    ///
+    /// Present only if cache states were benchmarked.
+    pub revalidation: Option<Samples>,
+}
+
+/// Durations of all repetitions of a single measurement, sorted in ascending order.
+#[derive(Debug, Clone, Default)]
+pub struct Samples(Vec<Duration>);
+
+impl Samples {
+    pub(crate) fn push(&mut self, sample: Duration) {
+        let index = self.0.partition_point(|s| *s <= sample);
+        self.0.insert(index, sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn min(&self) -> Duration {
+        self.0[0]
+    }
+
+    pub fn median(&self) -> Duration {
+        self.percentile(50)
+    }
+
+    pub fn p95(&self) -> Duration {
+        self.percentile(95)
+    }
+
+    /// Nearest-rank percentile of the samples.
+    fn percentile(&self, percentile: usize) -> Duration {
+        let rank = (percentile * self.0.len()).div_ceil(100);
+        self.0[rank.saturating_sub(1)]
+    }
+}
+
+impl BenchReport {
+    /// Returns speedup of the median cold time of `stats` relative to the first run of the sweep.
+    pub fn speedup(&self, stats: &ThreadCountStats) -> f64 {
+        self.runs[0].cold.median().as_secs_f64() / stats.cold.median().as_secs_f64()
+    }
+
+    /// Returns speedup of `stats` divided by the relative increase of the number of threads.
+    ///
+    /// Efficiency of `1.0` means perfect linear scaling.
+    pub fn efficiency(&self, stats: &ThreadCountStats) -> f64 {
+        let threads_ratio = stats.threads.get() as f64 / self.runs[0].threads.get() as f64;
+        self.speedup(stats) / threads_ratio
+    }
+
+    /// Formats the report as a human-readable table.
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:>8} {:>8} {:>12} {:>12} {:>12} {:>8} {:>10} {:>12} {:>12}\n",
+            "limit",
+            "threads",
+            "min",
+            "median",
+            "p95",
+            "speedup",
+            "efficiency",
+            "warm median",
+            "reval median",
+        );
+        for stats in &self.runs {
+            let format_median = |samples: &Option<Samples>| {
+                samples.as_ref().map_or("-".to_string(), |samples| {
+                    format!("{:.2?}", samples.median())
+                })
+            };
+
+            writeln!(
+                table,
+                "{:>8} {:>8} {:>12.2?} {:>12.2?} {:>12.2?} {:>8.2} {:>10.2} {:>12} {:>12}",
+                stats.threads_limit,
+                stats.threads,
+                stats.cold.min(),
+                stats.cold.median(),
+                stats.cold.p95(),
+                self.speedup(stats),
+                self.efficiency(stats),
+                format_median(&stats.warm),
+                format_median(&stats.revalidation),
+            )
+            .unwrap();
+        }
+        table
+    }
+
+    /// Formats the report as CSV with times in milliseconds.
+    ///
+    /// Warm and revalidation columns are left empty if cache states were not benchmarked.
+    pub fn csv(&self) -> String {
+        let mut csv = "threads_limit,threads,runs,min_ms,median_ms,p95_ms,speedup,efficiency,\
+                       warm_median_ms,revalidation_median_ms\n"
+            .to_string();
+        for stats in &self.runs {
+            let format_median = |samples: &Option<Samples>| {
+                samples.as_ref().map_or(String::new(), |samples| {
+                    format!("{:.3}", samples.median().as_secs_f64() * 1000.)
+                })
+            };
+
+            writeln!(
+                csv,
+                "{},{},{},{:.3},{:.3},{:.3},{:.4},{:.4},{},{}",
+                stats.threads_limit,
+                stats.threads,
+                stats.cold.len(),
+                stats.cold.min().as_secs_f64() * 1000.,
+                stats.cold.median().as_secs_f64() * 1000.,
+                stats.cold.p95().as_secs_f64() * 1000.,
+                self.speedup(stats),
+                self.efficiency(stats),
+                format_median(&stats.warm),
+                format_median(&stats.revalidation),
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+/// Runs diagnostics calculation `runs` times for each thread limit from `threads_limits`.
+///
+/// Every run is done on a db freshly loaded from `manifest_path` with `load_options`, so the main
+/// measurements are done with a cold cache. Only the diagnostics calculation itself is measured.
+/// If `load_options` request writing crate cache files, they are written once before all runs.
+///
+/// If `cache_states` is set, each run additionally calculates diagnostics again on the same db
+/// (warm cache) and once more after a no-op input change (revalidation), which simulates the LS
+/// steady state.
+///
+/// Every measurement is printed with the peak RSS reached since the start of its run, including
+/// the loading of the db.
+pub fn bench_diagnostics(
+    manifest_path: &Path,
+    load_options: &LoadOptions,
+    threads_limits: &[NonZero<usize>],
+    runs: NonZero<usize>,
+    cache_states: bool,
+) -> anyhow::Result<BenchReport> {
+    ensure!(
+        !threads_limits.is_empty(),
+        "at least one thread limit is required"
+    );
+    ensure!(
+        !load_options.lazy_dependencies,
+        "lazy loading of dependencies cannot be benchmarked, as diagnostics are calculated for all \
+         crates"
+    );
+
+    // Cache files are written once up front, so that all runs load the same crates from them.
+    let load_options = &if load_options.write_cache {
+        load_scarb_project_with_options(manifest_path.to_path_buf(), load_options)?;
+        LoadOptions {
+            write_cache: false,
+            ..load_options.clone()
+        }
+    } else {
+        load_options.clone()
+    };
+
+    let mut report = BenchReport { runs: vec![] };
+
+    for &threads_limit in threads_limits {
+        let mut cold = Samples::default();
+        let mut warm = cache_states.then(Samples::default);
+        let mut revalidation = cache_states.then(Samples::default);
+        let mut threads = threads_limit;
+
+        for run in 1..=runs.get() {
+            reset_peak_rss();
+            let mut db =
+                load_scarb_project_with_options(manifest_path.to_path_buf(), load_options)?.db;
+
+            let elapsed = time_diagnostics(&db, threads_limit, &mut threads);
+            eprintln!(
+                "threads limit {threads_limit}, run {run}/{runs}, cold: {elapsed:.2?}, peak RSS: \
+                 {}",
+                format_peak_rss(peak_rss())
+            );
+            cold.push(elapsed);
+
+            if let Some(warm) = &mut warm {
+                let elapsed = time_diagnostics(&db, threads_limit, &mut threads);
+                eprintln!(
+                    "threads limit {threads_limit}, run {run}/{runs}, warm: {elapsed:.2?}, peak \
+                     RSS: {}",
+                    format_peak_rss(peak_rss())
+                );
+                warm.push(elapsed);
+            }
+
+            if let Some(revalidation) = &mut revalidation {
+                bump_revision(&mut db);
+
+                let elapsed = time_diagnostics(&db, threads_limit, &mut threads);
+                eprintln!(
+                    "threads limit {threads_limit}, run {run}/{runs}, revalidation: \
+                     {elapsed:.2?}, peak RSS: {}",
+                    format_peak_rss(peak_rss())
+                );
+                revalidation.push(elapsed);
+            }
+        }
+
+        report.runs.push(ThreadCountStats {
+            threads_limit,
+            threads,
+            cold,
+            warm,
+            revalidation,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Measures a single diagnostics calculation, excluding the thread pool setup.
+///
+/// Stores the number of threads actually spawned by the pool in `threads`.
+fn time_diagnostics(
+    db: &RootDatabase,
+    threads_limit: NonZero<usize>,
+    threads: &mut NonZero<usize>,
+) -> Duration {
+    let diag_controller = DiagnosticController::new(threads_limit);
+    *threads = diag_controller.parallelism();
+
+    let (_, elapsed) = diag_controller
+        .timed(|diag_controller| diag_controller.calculate_diagnostics_for_all_files(db));
+    elapsed
+}
+
+/// Sets the content of the first crate's main file to its current value.
+///
+/// The value does not change, but salsa still starts a new revision, so all memoized queries have
+/// to be revalidated before their results can be reused.
+fn bump_revision(db: &mut RootDatabase) {
+    let Some(crate_id) = db.crates().into_iter().next() else {
+        return;
+    };
+    let Ok(file_id) = db.module_main_file(ModuleId::CrateRoot(crate_id)) else {
+        return;
+    };
+
+    let content = db.file_content(file_id);
+    db.override_file_content(file_id, content);
+}
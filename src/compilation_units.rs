@@ -0,0 +1,182 @@
+use crate::diagnostics::DiagnosticController;
+use crate::project::extract_compilation_unit_crates;
+use crate::{LoadOptions, load_crates, load_scarb_metadata};
+use cairo_lang_utils::smol_str::SmolStr;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::NonZero;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Summary of diagnostics calculated for a single compilation unit.
+#[derive(Debug, Clone)]
+pub struct CompilationUnitSummary {
+    /// Id of the compilation unit from Scarb metadata.
+    pub id: String,
+
+    /// Kind and name of the target the compilation unit builds, e.g. `test hello_unittest`.
+    pub target: String,
+
+    /// Number of diagnostics found in workspace crates of the compilation unit.
+    pub diagnostics: usize,
+
+    /// Wall time of loading the compilation unit and calculating diagnostics.
+    pub duration: Duration,
+}
+
+/// A diagnostic reported for a crate in some compilation units containing it, but not in others.
+#[derive(Debug, Clone)]
+pub struct DiagnosticDifference {
+    pub crate_name: SmolStr,
+    pub diagnostic: String,
+
+    /// Ids of compilation units in which the diagnostic is reported.
+    pub present_in: Vec<String>,
+
+    /// Ids of compilation units containing the crate, in which the diagnostic is not reported.
+    pub missing_in: Vec<String>,
+}
+
+/// Result of analysing each compilation unit of a workspace in a separate db.
+#[derive(Debug, Clone)]
+pub struct CompilationUnitsReport {
+    pub units: Vec<CompilationUnitSummary>,
+    pub differences: Vec<DiagnosticDifference>,
+}
+
+impl fmt::Display for CompilationUnitsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Compilation units: {}", self.units.len())?;
+        for unit in &self.units {
+            writeln!(
+                f,
+                "  {} ({}): {} diagnostics, {:.2?}",
+                unit.id, unit.target, unit.diagnostics, unit.duration
+            )?;
+        }
+
+        writeln!(
+            f,
+            "Diagnostics differing between compilation units: {}",
+            self.differences.len()
+        )?;
+        for difference in &self.differences {
+            writeln!(
+                f,
+                "crate {}, reported in: {}, missing in: {}",
+                difference.crate_name,
+                difference.present_in.join(", "),
+                difference.missing_in.join(", ")
+            )?;
+            write!(f, "{}", difference.diagnostic)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads each compilation unit of the Scarb workspace under `manifest_path` into its own db,
+/// calculates diagnostics for its workspace crates, and compares the results between compilation
+/// units.
+///
+/// [`extract_crates`] merges dependencies and cfg sets of a crate from all compilation units it
+/// is a component of, and enables test cfgs everywhere, which may hide diagnostics reported only
+/// under one of them, e.g. in code valid only with `cfg(test)`. Here, each db is set up only with
+/// crates of a single compilation unit and cfg sets Scarb sets for it.
+///
+/// [`extract_crates`]: crate::project::extract_crates
+///
+/// Diagnostics are compared per crate, only between compilation units containing that crate.
+pub fn calculate_diagnostics_per_compilation_unit(
+    manifest_path: &Path,
+    options: &LoadOptions,
+    threads_limit: NonZero<usize>,
+) -> anyhow::Result<CompilationUnitsReport> {
+    let metadata = load_scarb_metadata(manifest_path, options)?;
+
+    let mut units = vec![];
+    // Crate name -> compilation unit id -> diagnostics of the crate.
+    let mut diagnostics_by_crate: BTreeMap<SmolStr, BTreeMap<String, Vec<String>>> =
+        BTreeMap::new();
+
+    for compilation_unit in &metadata.compilation_units {
+        if compilation_unit.target.kind == "cairo-plugin" {
+            continue;
+        }
+
+        let now = Instant::now();
+
+        let project = load_crates(
+            extract_compilation_unit_crates(&metadata, compilation_unit),
+            options,
+        )?;
+
+        let (unit_diagnostics, _) =
+            DiagnosticController::new(threads_limit).timed(|diag_controller| {
+                let mut unit_diagnostics = 0;
+                for cr in project.crates.iter().filter(|cr| cr.is_workspace_member) {
+                    let diagnostics = diag_controller
+                        .calculate_diagnostics_for_crates(&project.db, &[cr.crate_id(&project.db)]);
+                    unit_diagnostics += diagnostics.len();
+
+                    diagnostics_by_crate
+                        .entry(cr.name.clone())
+                        .or_default()
+                        .insert(compilation_unit.id.to_string(), diagnostics);
+                }
+                unit_diagnostics
+            });
+
+        units.push(CompilationUnitSummary {
+            id: compilation_unit.id.to_string(),
+            target: format!(
+                "{} {}",
+                compilation_unit.target.kind, compilation_unit.target.name
+            ),
+            diagnostics: unit_diagnostics,
+            duration: now.elapsed(),
+        });
+    }
+
+    let differences = diagnostics_by_crate
+        .into_iter()
+        .flat_map(|(crate_name, diagnostics_by_unit)| {
+            differences_in_crate(crate_name, diagnostics_by_unit)
+        })
+        .collect();
+
+    Ok(CompilationUnitsReport { units, differences })
+}
+
+/// Returns diagnostics of a crate not reported in all compilation units in `diagnostics_by_unit`.
+fn differences_in_crate(
+    crate_name: SmolStr,
+    diagnostics_by_unit: BTreeMap<String, Vec<String>>,
+) -> Vec<DiagnosticDifference> {
+    // Diagnostic -> ids of compilation units reporting it.
+    let mut units_by_diagnostic: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+    for (unit, diagnostics) in &diagnostics_by_unit {
+        for diagnostic in diagnostics {
+            let units = units_by_diagnostic.entry(diagnostic).or_default();
+            // The same diagnostic may be reported multiple times in a unit.
+            if units.last() != Some(&unit) {
+                units.push(unit);
+            }
+        }
+    }
+
+    units_by_diagnostic
+        .into_iter()
+        .filter(|(_, units)| units.len() < diagnostics_by_unit.len())
+        .map(|(diagnostic, units)| DiagnosticDifference {
+            crate_name: crate_name.clone(),
+            diagnostic: diagnostic.clone(),
+            present_in: units.iter().map(|unit| (*unit).clone()).collect(),
+            missing_in: diagnostics_by_unit
+                .keys()
+                .filter(|unit| !units.contains(unit))
+                .cloned()
+                .collect(),
+        })
+        .collect()
+}
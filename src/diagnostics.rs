@@ -1,125 +1,403 @@
 use cairo_lang_compiler::db::RootDatabase;
 use cairo_lang_defs::db::DefsGroup;
-use cairo_lang_defs::ids::ModuleId;
+use cairo_lang_defs::ids::{FunctionWithBodyId, ModuleId};
 use cairo_lang_diagnostics::{DiagnosticEntry, Diagnostics};
 use cairo_lang_filesystem::db::FilesGroup;
-use cairo_lang_filesystem::ids::{FileId, FileLongId};
+use cairo_lang_filesystem::ids::{CrateId, FileId, FileLongId};
 use cairo_lang_lowering::db::LoweringGroup;
 use cairo_lang_parser::db::ParserGroup;
 use cairo_lang_semantic::db::SemanticGroup;
+use cairo_lang_syntax::node::TypedStablePtr;
+use cairo_lang_syntax::node::db::SyntaxGroup;
 use cairo_lang_utils::{LookupIntern, Upcast};
+use itertools::Itertools;
 use std::collections::{HashSet, VecDeque};
 use std::iter;
 use std::iter::zip;
 use std::num::NonZero;
+use std::time::{Duration, Instant};
 
 mod pool;
+mod scheduling;
 
 use crate::diagnostics::pool::Pool;
+use crate::diagnostics::scheduling::{ThreadCpuTimes, schedule};
+
+pub use crate::diagnostics::scheduling::{SchedulingReport, SchedulingStrategy, WorkerStats};
 
 pub struct DiagnosticController {
     pool: Pool,
+    scheduling: SchedulingStrategy,
+    warm_up_crates: Vec<CrateId>,
 }
 
 impl DiagnosticController {
     pub fn new(threads_limit: NonZero<usize>) -> Self {
         Self {
             pool: Pool::new(threads_limit.get()),
+            scheduling: SchedulingStrategy::default(),
+            warm_up_crates: vec![],
         }
     }
 
-    pub fn calculate_diagnostics_for_all_files(&self, db: &RootDatabase) {
-        let files = find_all_files_from_all_crates(db);
-        let files_batches = batches(&files, self.pool.parallelism());
+    /// Makes the controller split files between workers according to `strategy`.
+    pub fn with_scheduling(mut self, strategy: SchedulingStrategy) -> Self {
+        self.scheduling = strategy;
+        self
+    }
+
+    /// Makes the controller calculate diagnostics for files of `crates` on the calling thread
+    /// before spawning workers, so that workers do not block on each other computing queries of
+    /// these crates at the start of each calculation.
+    pub fn with_warm_up(mut self, crates: Vec<CrateId>) -> Self {
+        self.warm_up_crates = crates;
+        self
+    }
+
+    /// Returns the number of workers calculating diagnostics concurrently.
+    pub fn parallelism(&self) -> NonZero<usize> {
+        self.pool.parallelism()
+    }
+
+    /// Runs `calculate` with the controller and drops it afterwards, so that the returned duration
+    /// covers the calculation until all threads are joined, without the thread pool setup.
+    pub fn timed<T>(self, calculate: impl FnOnce(&Self) -> T) -> (T, Duration) {
+        let now = Instant::now();
+
+        let result = calculate(&self);
+
+        // Drop to make sure all threads are joined.
+        drop(self);
+
+        (result, now.elapsed())
+    }
+
+    /// Calculates diagnostics for all files from all crates loaded into the db and returns them
+    /// formatted and sorted, so the result does not depend on how work was split between workers.
+    pub fn calculate_diagnostics_for_all_files(&self, db: &RootDatabase) -> Vec<String> {
+        self.calculate(db, &db.crates(), false).diagnostics
+    }
+
+    /// Same as [`Self::calculate_diagnostics_for_all_files`], but only files from `crates` are
+    /// scheduled. Other crates are analysed only as far as they are needed by the scheduled ones.
+    pub fn calculate_diagnostics_for_crates(
+        &self,
+        db: &RootDatabase,
+        crates: &[CrateId],
+    ) -> Vec<String> {
+        self.calculate(db, crates, false).diagnostics
+    }
+
+    /// Same as [`Self::calculate_diagnostics_for_all_files`], but additionally measures the
+    /// duration of each diagnostics query called by the workers, and the time each worker spent
+    /// blocked.
+    pub fn calculate_diagnostics_with_query_timings(
+        &self,
+        db: &RootDatabase,
+    ) -> (Vec<String>, Vec<QueryTiming>, Vec<WorkerStats>) {
+        let calculation = self.calculate(db, &db.crates(), true);
+        (
+            calculation.diagnostics,
+            calculation.timings,
+            calculation.workers,
+        )
+    }
+
+    /// Same as [`Self::calculate_diagnostics_for_all_files`], but additionally reports how much
+    /// time each worker spent blocked with the scheduling strategy of the controller.
+    pub fn calculate_diagnostics_with_scheduling_report(
+        &self,
+        db: &RootDatabase,
+    ) -> (Vec<String>, SchedulingReport) {
+        let now = Instant::now();
+        let calculation = self.calculate(db, &db.crates(), false);
+
+        let report = SchedulingReport {
+            strategy: self.scheduling,
+            total: now.elapsed(),
+            workers: calculation.workers,
+        };
+
+        (calculation.diagnostics, report)
+    }
+
+    /// Same as [`Self::calculate_diagnostics_for_all_files`], but additionally returns the
+    /// duration of the warm-up phase.
+    pub fn calculate_diagnostics_with_warm_up_time(
+        &self,
+        db: &RootDatabase,
+    ) -> (Vec<String>, Duration) {
+        let calculation = self.calculate(db, &db.crates(), false);
+        (calculation.diagnostics, calculation.warm_up)
+    }
+
+    fn calculate(
+        &self,
+        db: &RootDatabase,
+        crates: &[CrateId],
+        record_timings: bool,
+    ) -> Calculation {
+        let warm_up = self.warm_up(db);
+
+        let files = find_all_files_from_crates(db, crates);
+        let files_batches = schedule(db, files, self.pool.parallelism(), self.scheduling);
 
         let db_snapshots = iter::from_fn(|| Some(salsa::Snapshot::new(db.snapshot())))
             .take(self.pool.parallelism().get())
             .collect();
 
-        self.spawn_refresh_workers(files_batches, db_snapshots);
+        let (diags_sender, diags_receiver) = crossbeam_channel::unbounded();
+        let (stats_sender, stats_receiver) = crossbeam_channel::unbounded();
+        self.spawn_refresh_workers(
+            files_batches,
+            db_snapshots,
+            diags_sender,
+            stats_sender,
+            record_timings,
+        );
+
+        // The channel closes once all workers are done and drop their senders.
+        let mut diagnostics = vec![];
+        let mut timings = vec![];
+        for (file_diags, file_timings) in diags_receiver {
+            diagnostics.extend(file_diags);
+            timings.extend(file_timings);
+        }
+        diagnostics.sort();
+
+        // All workers sent their stats before dropping their diagnostics senders.
+        let workers = stats_receiver
+            .try_iter()
+            .sorted_by_key(|stats: &WorkerStats| stats.worker)
+            .collect();
+
+        Calculation {
+            diagnostics,
+            timings,
+            workers,
+            warm_up,
+        }
+    }
+
+    /// Calculates diagnostics for files of warm-up crates on the calling thread and returns how
+    /// long it took.
+    ///
+    /// Results are discarded. Workers calculate them again, but only read them from memoized
+    /// queries of the db they were snapshotted from.
+    fn warm_up(&self, db: &RootDatabase) -> Duration {
+        let now = Instant::now();
+        let mut timer = QueryTimer::new(0, false);
+        for (_, file) in find_all_files_from_crates(db, &self.warm_up_crates) {
+            calculate_diags_for_file(db, file, &mut timer);
+        }
+        now.elapsed()
     }
 
     fn spawn_refresh_workers(
         &self,
         files_batches: Vec<Vec<FileId>>,
         db_snapshots: Vec<salsa::Snapshot<RootDatabase>>,
+        diags_sender: crossbeam_channel::Sender<(Vec<String>, Vec<QueryTiming>)>,
+        stats_sender: crossbeam_channel::Sender<WorkerStats>,
+        record_timings: bool,
     ) {
         assert_eq!(files_batches.len(), db_snapshots.len());
-        for (batch, snapshot) in zip(files_batches, db_snapshots) {
+        for (worker, (batch, snapshot)) in zip(files_batches, db_snapshots).enumerate() {
+            let diags_sender = diags_sender.clone();
+            let stats_sender = stats_sender.clone();
             self.pool.spawn(move || {
+                let now = Instant::now();
+                let cpu_times_start = ThreadCpuTimes::current();
+
+                let mut timer = QueryTimer::new(worker, record_timings);
+                let files = batch.len();
                 for file in batch {
-                    calculate_diags_for_file(&snapshot, file);
+                    let diags = calculate_diags_for_file(&snapshot, file, &mut timer);
+                    diags_sender.send((diags, timer.take())).unwrap();
                 }
+
+                let wall = now.elapsed();
+                let blocked = cpu_times_start
+                    .zip(ThreadCpuTimes::current())
+                    .map(|(start, end)| end.blocked_since(&start, wall));
+                stats_sender
+                    .send(WorkerStats {
+                        worker,
+                        files,
+                        wall,
+                        blocked,
+                    })
+                    .unwrap();
             });
         }
     }
 }
 
-fn find_all_files_from_all_crates(db: &RootDatabase) -> Vec<FileId> {
+/// Results of a single diagnostics calculation.
+struct Calculation {
+    /// Formatted diagnostics, sorted.
+    diagnostics: Vec<String>,
+
+    /// Durations of query calls, empty unless recording them was requested.
+    timings: Vec<QueryTiming>,
+
+    workers: Vec<WorkerStats>,
+
+    /// Duration of the warm-up phase, zero if no warm-up crates were set.
+    warm_up: Duration,
+}
+
+/// Duration of a single diagnostics query call made by a worker.
+///
+/// The duration includes the time of all queries computed as dependencies of the called one,
+/// together with the time spent waiting for other workers computing the same queries.
+#[derive(Debug, Clone)]
+pub struct QueryTiming {
+    /// Name of the called query.
+    pub query: &'static str,
+
+    /// Path of the module or file the query was called for.
+    pub target: String,
+
+    /// Index of the worker that called the query.
+    pub worker: usize,
+
+    pub duration: Duration,
+}
+
+/// Measures durations of query calls made by a single worker, if enabled.
+struct QueryTimer {
+    worker: usize,
+
+    enabled: bool,
+
+    timings: Vec<QueryTiming>,
+}
+
+impl QueryTimer {
+    fn new(worker: usize, enabled: bool) -> Self {
+        Self {
+            worker,
+            enabled,
+            timings: vec![],
+        }
+    }
+
+    /// Calls `f` and records its duration under `query` and the target described by `target`.
+    fn time<T>(
+        &mut self,
+        query: &'static str,
+        target: impl FnOnce() -> String,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        if !self.enabled {
+            return f();
+        }
+
+        let now = Instant::now();
+        let result = f();
+        let duration = now.elapsed();
+
+        self.timings.push(QueryTiming {
+            query,
+            target: target(),
+            worker: self.worker,
+            duration,
+        });
+
+        result
+    }
+
+    /// Returns timings recorded so far and clears them.
+    fn take(&mut self) -> Vec<QueryTiming> {
+        std::mem::take(&mut self.timings)
+    }
+}
+
+/// Returns on disk main files of all modules from `crates`, together with their crates, sorted by
+/// path.
+fn find_all_files_from_crates(db: &RootDatabase, crates: &[CrateId]) -> Vec<(CrateId, FileId)> {
     let mut result = HashSet::new();
-    for crate_id in db.crates() {
+    for &crate_id in crates {
         for module_id in db.crate_modules(crate_id).iter() {
             // Schedule only on disk module main files for refreshing.
             // All other related files will be refreshed along with it in a single job.
             if let Ok(file) = db.module_main_file(*module_id) {
                 if matches!(file.lookup_intern(db), FileLongId::OnDisk(_)) {
-                    result.insert(file);
+                    result.insert((crate_id, file));
                 }
             }
         }
     }
-    result.into_iter().collect()
-}
-
-fn batches(input: &[FileId], n: NonZero<usize>) -> Vec<Vec<FileId>> {
-    let n = n.get();
-    (1..=n)
-        .map(|offset| input.iter().copied().skip(offset - 1).step_by(n).collect())
+    // Sort to make batches independent of `HashSet` iteration order.
+    result
+        .into_iter()
+        .sorted_by_cached_key(|(_, file)| file.full_path(db))
         .collect()
 }
 
 /// Calculates all diagnostics kinds by processing an on disk `root_on_disk_file` together with
 /// virtual files that are its descendants.
-fn calculate_diags_for_file(db: &RootDatabase, root_on_disk_file: FileId) {
+///
+/// Returns formatted diagnostics entries.
+fn calculate_diags_for_file(
+    db: &RootDatabase,
+    root_on_disk_file: FileId,
+    timer: &mut QueryTimer,
+) -> Vec<String> {
     let Some((files_to_process, modules_to_process)) =
         file_and_subfiles_with_corresponding_modules(db, root_on_disk_file)
     else {
         eprintln!("error during diagnostics calculation");
-        return;
+        return vec![];
     };
 
-    fn print_diags<T: DiagnosticEntry>(db: &<T as DiagnosticEntry>::DbType, diags: Diagnostics<T>) {
+    let mut result = vec![];
+
+    fn format_diags<T: DiagnosticEntry>(
+        db: &<T as DiagnosticEntry>::DbType,
+        diags: Diagnostics<T>,
+        result: &mut Vec<String>,
+    ) {
         for entry in diags.format_with_severity(db, &Default::default()) {
             if !entry.is_empty() {
-                eprint!("{entry}")
+                result.push(entry.to_string());
             }
         }
     }
 
     for module_id in modules_to_process.into_iter() {
-        let diags = db
-            .module_semantic_diagnostics(module_id)
-            .unwrap_or_default();
-        print_diags(db.upcast(), diags);
+        let module_path = || module_id.full_path(db);
 
-        let diags = db
-            .module_lowering_diagnostics(module_id)
-            .unwrap_or_default();
-        print_diags(db.upcast(), diags);
+        let diags = timer.time("module_semantic_diagnostics", module_path, || {
+            db.module_semantic_diagnostics(module_id)
+        });
+        format_diags(db.upcast(), diags.unwrap_or_default(), &mut result);
+
+        let diags = timer.time("module_lowering_diagnostics", module_path, || {
+            db.module_lowering_diagnostics(module_id)
+        });
+        format_diags(db.upcast(), diags.unwrap_or_default(), &mut result);
     }
 
     for file_id in files_to_process.into_iter() {
-        let diags = db.file_syntax_diagnostics(file_id);
-        print_diags(db.upcast(), diags);
+        let diags = timer.time(
+            "file_syntax_diagnostics",
+            || file_id.full_path(db),
+            || db.file_syntax_diagnostics(file_id),
+        );
+        format_diags(db.upcast(), diags, &mut result);
     }
+
+    result
 }
 
 /// **DISCLAIMER**: this is a query in LS.
 ///
 /// Collects `file` and all its descendants together with modules from all these files.
 ///
-/// **CAVEAT**: it does not collect descendant files that come from inline macros - it will when
-/// the compiler moves inline macros resolving to [`DefsGroup`].
+/// Descendant files that come from inline macros are discovered from semantic data of the
+/// collected modules, see [`module_inline_macro_files`].
 fn file_and_subfiles_with_corresponding_modules(
     db: &dyn SemanticGroup,
     file: FileId,
@@ -153,5 +431,88 @@ fn file_and_subfiles_with_corresponding_modules(
             }
         }
     }
+
+    // Inline macros are expanded during semantic analysis of function bodies, so their files are
+    // not reachable through `module_files`. They cannot define modules, hence there is no need to
+    // repeat the procedure above for them.
+    for module_id in modules.iter() {
+        files.extend(module_inline_macro_files(db, *module_id));
+    }
+
     Some((files, modules))
 }
+
+/// Collects virtual files generated by inline macros expanded in bodies of functions defined
+/// directly in `module_id`.
+///
+/// Expanded code is analysed as a part of the function body, so any expression coming from an
+/// inline macro points to the virtual file generated for the expansion.
+///
+/// Items whose semantic data cannot be computed are skipped, without affecting the other items.
+/// Their errors are reported by diagnostics queries anyway.
+fn module_inline_macro_files(db: &dyn SemanticGroup, module_id: ModuleId) -> HashSet<FileId> {
+    let free_functions = db
+        .module_free_functions_ids(module_id)
+        .map(|ids| ids.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(FunctionWithBodyId::Free);
+
+    let impl_functions = db
+        .module_impls_ids(module_id)
+        .map(|ids| ids.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|impl_def_id| db.impl_functions(impl_def_id).ok())
+        .flat_map(|functions| functions.values().copied().collect::<Vec<_>>())
+        .map(FunctionWithBodyId::Impl);
+
+    // Trait functions have bodies only if they provide a default implementation.
+    // Missing bodies are reported as errors, which are skipped here.
+    let trait_functions = db
+        .module_traits_ids(module_id)
+        .map(|ids| ids.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|trait_id| db.trait_functions(trait_id).ok())
+        .flat_map(|functions| functions.values().copied().collect::<Vec<_>>())
+        .map(FunctionWithBodyId::Trait);
+
+    let syntax_db: &dyn SyntaxGroup = db.upcast();
+
+    free_functions
+        .chain(impl_functions)
+        .chain(trait_functions)
+        .filter_map(|function_id| db.function_body(function_id).ok())
+        .flat_map(|body| {
+            body.arenas
+                .exprs
+                .iter()
+                .map(|(_, expr)| expr.stable_ptr().untyped().file_id(syntax_db))
+                .collect::<Vec<_>>()
+        })
+        .filter(|file_id| matches!(file_id.lookup_intern(db), FileLongId::Virtual(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiagnosticController;
+    use std::num::NonZero;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn timed_returns_result_and_covers_calculation() {
+        let diag_controller = DiagnosticController::new(NonZero::new(2).unwrap());
+        let parallelism = diag_controller.parallelism();
+
+        let (result, elapsed) = diag_controller.timed(|diag_controller| {
+            thread::sleep(Duration::from_millis(20));
+            diag_controller.parallelism()
+        });
+
+        assert_eq!(result, parallelism);
+        assert!(elapsed >= Duration::from_millis(20), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,538 @@
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_filesystem::db::FilesGroup;
+use cairo_lang_filesystem::ids::{CrateId, CrateLongId, FileId};
+use cairo_lang_utils::Intern;
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::num::NonZero;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A way of splitting files between workers calculating diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchedulingStrategy {
+    /// Files sorted by path are dealt to workers in turns.
+    #[default]
+    RoundRobin,
+
+    /// All files of a crate are given to a single worker. Crates, largest first, are given to the
+    /// least loaded worker.
+    ByCrate,
+
+    /// Files are sorted so that files of a crate come after files of all its dependencies, and
+    /// then dealt to workers in turns.
+    Topological,
+
+    /// Files, largest first, are given to the least loaded worker.
+    LargestFirst,
+}
+
+impl SchedulingStrategy {
+    pub const ALL: [Self; 4] = [
+        Self::RoundRobin,
+        Self::ByCrate,
+        Self::Topological,
+        Self::LargestFirst,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::RoundRobin => "round-robin",
+            Self::ByCrate => "by-crate",
+            Self::Topological => "topological",
+            Self::LargestFirst => "largest-first",
+        }
+    }
+}
+
+impl fmt::Display for SchedulingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SchedulingStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == s)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown scheduling strategy: {s}; available strategies are: {}",
+                    Self::ALL.iter().join(", ")
+                )
+            })
+    }
+}
+
+/// Splits `files`, sorted by path, into a batch for each of `n` workers according to `strategy`.
+pub(super) fn schedule(
+    db: &RootDatabase,
+    files: Vec<(CrateId, FileId)>,
+    n: NonZero<usize>,
+    strategy: SchedulingStrategy,
+) -> Vec<Vec<FileId>> {
+    match strategy {
+        SchedulingStrategy::RoundRobin => {
+            round_robin(files.into_iter().map(|(_, file)| file).collect(), n)
+        }
+        SchedulingStrategy::ByCrate => {
+            let crates = files
+                .into_iter()
+                .into_group_map()
+                .into_iter()
+                // Sort to make batches independent of `HashMap` iteration order. Crates can share
+                // a name, so the path of the first file of each crate breaks ties.
+                .sorted_by_cached_key(|(crate_id, files)| {
+                    (
+                        crate_id.name(db),
+                        files.first().map(|file| file.full_path(db)),
+                    )
+                })
+                .map(|(_, files)| {
+                    let size = files.iter().map(|file| file_size(db, *file)).sum();
+                    (files, size)
+                })
+                .collect();
+            least_loaded_first(crates, n)
+        }
+        SchedulingStrategy::Topological => {
+            let depths = crate_depths(db);
+            let files = files
+                .into_iter()
+                // The sort is stable, so files of each crate stay sorted by path.
+                .sorted_by_key(|(crate_id, _)| depths.get(crate_id).copied().unwrap_or_default())
+                .map(|(_, file)| file)
+                .collect();
+            round_robin(files, n)
+        }
+        SchedulingStrategy::LargestFirst => {
+            let files = files
+                .into_iter()
+                .map(|(_, file)| (vec![file], file_size(db, file)))
+                .collect();
+            least_loaded_first(files, n)
+        }
+    }
+}
+
+fn round_robin(files: Vec<FileId>, n: NonZero<usize>) -> Vec<Vec<FileId>> {
+    let n = n.get();
+    (1..=n)
+        .map(|offset| files.iter().copied().skip(offset - 1).step_by(n).collect())
+        .collect()
+}
+
+/// Gives groups of files, largest first, to the worker with the smallest total size so far.
+fn least_loaded_first(groups: Vec<(Vec<FileId>, usize)>, n: NonZero<usize>) -> Vec<Vec<FileId>> {
+    let mut batches = vec![(vec![], 0); n.get()];
+
+    // The sort is stable, so groups of equal sizes keep their order.
+    for (files, size) in groups.into_iter().sorted_by_key(|(_, size)| Reverse(*size)) {
+        let (batch, load) = batches
+            .iter_mut()
+            .min_by_key(|(_, load)| *load)
+            .expect("there is at least one batch");
+        batch.extend(files);
+        *load += size;
+    }
+
+    batches.into_iter().map(|(batch, _)| batch).collect()
+}
+
+/// Size of the file content in bytes, used as an estimate of the work needed to analyse it.
+fn file_size(db: &RootDatabase, file: FileId) -> usize {
+    db.file_content(file).map_or(0, |content| content.len())
+}
+
+/// Returns the length of the longest dependency chain starting at each crate in the db.
+///
+/// Crates with no dependencies have depth `0`, so sorting by depth puts dependencies first.
+fn crate_depths(db: &RootDatabase) -> HashMap<CrateId, usize> {
+    fn depth(db: &RootDatabase, crate_id: CrateId, depths: &mut HashMap<CrateId, usize>) -> usize {
+        if let Some(depth) = depths.get(&crate_id) {
+            return *depth;
+        }
+        // Guard against dependency cycles, which are reported by the compiler anyway.
+        depths.insert(crate_id, 0);
+
+        let dependencies = db
+            .crate_config(crate_id)
+            .map(|config| config.settings.dependencies)
+            .unwrap_or_default();
+
+        let crate_depth = dependencies
+            .into_iter()
+            .map(|(name, dependency)| {
+                let dependency_id = CrateLongId::Real {
+                    name: name.into(),
+                    discriminator: dependency.discriminator,
+                }
+                .intern(db);
+                depth(db, dependency_id, depths) + 1
+            })
+            .max()
+            .unwrap_or_default();
+
+        depths.insert(crate_id, crate_depth);
+        crate_depth
+    }
+
+    let mut depths = HashMap::new();
+    for crate_id in db.crates() {
+        depth(db, crate_id, &mut depths);
+    }
+    depths
+}
+
+/// Time a worker spent on its batch.
+#[derive(Debug, Clone)]
+pub struct WorkerStats {
+    /// Index of the worker.
+    pub worker: usize,
+
+    /// Number of files in the batch of the worker.
+    pub files: usize,
+
+    /// Wall time from the start of the worker until it finished its batch.
+    pub wall: Duration,
+
+    /// Part of [`WorkerStats::wall`] spent neither running nor waiting for a CPU, i.e. waiting for
+    /// other workers computing queries needed by this one.
+    ///
+    /// `None` if it could not be measured, which is always the case outside Linux.
+    pub blocked: Option<Duration>,
+}
+
+/// Summary of a diagnostics calculation with a given scheduling strategy.
+#[derive(Debug, Clone)]
+pub struct SchedulingReport {
+    pub strategy: SchedulingStrategy,
+
+    /// Wall time of the whole calculation.
+    pub total: Duration,
+
+    /// Statistics of each worker, ordered by the worker index.
+    pub workers: Vec<WorkerStats>,
+}
+
+impl SchedulingReport {
+    /// Returns the sum of blocked time of all workers, if it was measured.
+    pub fn blocked(&self) -> Option<Duration> {
+        self.workers.iter().map(|worker| worker.blocked).sum()
+    }
+}
+
+impl fmt::Display for SchedulingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_blocked =
+            |blocked: Option<Duration>| blocked.map_or("-".to_string(), |b| format!("{b:.2?}"));
+
+        writeln!(f, "Scheduling strategy: {}", self.strategy)?;
+        writeln!(f, "Diagnostics calculation time: {:.2?}", self.total)?;
+        writeln!(f, "Total blocked time: {}", format_blocked(self.blocked()))?;
+        writeln!(
+            f,
+            "{:>8} {:>8} {:>12} {:>12}",
+            "worker", "files", "wall", "blocked"
+        )?;
+        for worker in &self.workers {
+            writeln!(
+                f,
+                "{:>8} {:>8} {:>12.2?} {:>12}",
+                worker.worker,
+                worker.files,
+                worker.wall,
+                format_blocked(worker.blocked)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Time the current thread spent running on a CPU and waiting in a run queue for one.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ThreadCpuTimes {
+    running: Duration,
+    waiting: Duration,
+}
+
+impl ThreadCpuTimes {
+    /// Reads the times of the current thread, which is possible only on Linux.
+    pub(super) fn current() -> Option<Self> {
+        let schedstat = fs::read_to_string("/proc/thread-self/schedstat").ok()?;
+        let mut fields = schedstat.split_whitespace().map(str::parse::<u64>);
+
+        Some(Self {
+            running: Duration::from_nanos(fields.next()?.ok()?),
+            waiting: Duration::from_nanos(fields.next()?.ok()?),
+        })
+    }
+
+    /// Returns the part of `wall` that the thread spent blocked between `start` and `self`.
+    pub(super) fn blocked_since(&self, start: &Self, wall: Duration) -> Duration {
+        let active = (self.running - start.running) + (self.waiting - start.waiting);
+        wall.saturating_sub(active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SchedulingStrategy, file_size, schedule};
+    use cairo_lang_compiler::db::RootDatabase;
+    use cairo_lang_filesystem::db::{
+        CrateConfiguration, CrateSettings, DependencySettings, Edition, FilesGroupEx,
+    };
+    use cairo_lang_filesystem::ids::{CrateId, CrateLongId, Directory, FileId};
+    use cairo_lang_utils::Intern;
+    use std::collections::HashSet;
+    use std::num::NonZero;
+    use std::path::PathBuf;
+
+    fn crate_id(db: &RootDatabase, name: &str, discriminator: &str) -> CrateId {
+        CrateLongId::Real {
+            name: name.into(),
+            discriminator: Some(discriminator.into()),
+        }
+        .intern(db)
+    }
+
+    /// Sets up files given as `(crate name, crate discriminator, path, size)`, sorted by path.
+    fn setup_files(files: &[(&str, &str, &str, usize)]) -> (RootDatabase, Vec<(CrateId, FileId)>) {
+        let mut db = RootDatabase::empty();
+
+        let files = files
+            .iter()
+            .map(|&(name, discriminator, path, size)| {
+                let crate_id = crate_id(&db, name, discriminator);
+                let file_id = FileId::new(&db, PathBuf::from(path));
+                db.override_file_content(file_id, Some("x".repeat(size).into()));
+                (crate_id, file_id)
+            })
+            .collect();
+
+        (db, files)
+    }
+
+    /// Sets up configurations of crates given as `(crate name, crate discriminator, dependencies)`,
+    /// where each dependency is given as `(crate name, crate discriminator)`.
+    fn setup_crates(db: &mut RootDatabase, crates: &[(&str, &str, &[(&str, &str)])]) {
+        for &(name, discriminator, dependencies) in crates {
+            let settings = CrateSettings {
+                name: Some(name.into()),
+                edition: Edition::default(),
+                version: None,
+                dependencies: dependencies
+                    .iter()
+                    .map(|&(name, discriminator)| {
+                        let dependency = DependencySettings {
+                            discriminator: Some(discriminator.into()),
+                        };
+                        (name.to_string(), dependency)
+                    })
+                    .collect(),
+                cfg_set: None,
+                experimental_features: Default::default(),
+            };
+            let crate_id = crate_id(db, name, discriminator);
+            db.set_crate_config(
+                crate_id,
+                Some(CrateConfiguration {
+                    root: Directory::Real(PathBuf::from("/").join(name)),
+                    settings,
+                    cache_file: None,
+                }),
+            );
+        }
+    }
+
+    /// Files of uneven sizes from crates forming a dependency chain `world -> hello -> dep 1.0.0`,
+    /// with `dep 2.0.0` standing aside. Sorting by path puts dependents before their dependencies.
+    fn test_files() -> (RootDatabase, Vec<(CrateId, FileId)>) {
+        let (mut db, files) = setup_files(&[
+            ("hello", "hello 1.0.0", "/a/hello/src/a.cairo", 30),
+            ("hello", "hello 1.0.0", "/a/hello/src/lib.cairo", 10),
+            ("dep", "dep 2.0.0", "/b/dep/src/lib.cairo", 50),
+            ("dep", "dep 1.0.0", "/c/dep/src/lib.cairo", 50),
+            ("dep", "dep 1.0.0", "/c/dep/src/x.cairo", 5),
+            ("world", "world 1.0.0", "/d/world/src/lib.cairo", 20),
+        ]);
+        setup_crates(
+            &mut db,
+            &[
+                ("world", "world 1.0.0", &[("hello", "hello 1.0.0")]),
+                ("hello", "hello 1.0.0", &[("dep", "dep 1.0.0")]),
+                ("dep", "dep 1.0.0", &[]),
+                ("dep", "dep 2.0.0", &[]),
+            ],
+        );
+
+        (db, files)
+    }
+
+    /// Returns paths of scheduled files, batch by batch.
+    fn paths(db: &RootDatabase, batches: &[Vec<FileId>]) -> Vec<Vec<String>> {
+        batches
+            .iter()
+            .map(|batch| batch.iter().map(|file| file.full_path(db)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn every_strategy_schedules_each_file_exactly_once() {
+        let (db, files) = test_files();
+        let expected: HashSet<_> = files.iter().map(|(_, file)| *file).collect();
+
+        for strategy in SchedulingStrategy::ALL {
+            for n in 1..=8 {
+                let batches = schedule(&db, files.clone(), NonZero::new(n).unwrap(), strategy);
+                let scheduled: Vec<_> = batches.iter().flatten().copied().collect();
+
+                assert_eq!(batches.len(), n, "{strategy} with {n} workers");
+                assert_eq!(scheduled.len(), files.len(), "{strategy} with {n} workers");
+                assert_eq!(
+                    scheduled.into_iter().collect::<HashSet<_>>(),
+                    expected,
+                    "{strategy} with {n} workers"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_strategy_is_deterministic() {
+        let (db, files) = test_files();
+
+        for strategy in SchedulingStrategy::ALL {
+            for n in 1..=4 {
+                let n = NonZero::new(n).unwrap();
+                let first = schedule(&db, files.clone(), n, strategy);
+
+                // Each call groups files in a new `HashMap` with a different iteration order.
+                for _ in 0..16 {
+                    assert_eq!(
+                        schedule(&db, files.clone(), n, strategy),
+                        first,
+                        "{strategy} with {n} workers"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn by_crate_keeps_files_of_a_crate_together() {
+        let (db, files) = test_files();
+
+        let batches = schedule(
+            &db,
+            files.clone(),
+            NonZero::new(4).unwrap(),
+            SchedulingStrategy::ByCrate,
+        );
+
+        for (crate_id, file) in &files {
+            let batch = batches.iter().find(|batch| batch.contains(file)).unwrap();
+            for (other_crate_id, other_file) in &files {
+                if other_crate_id == crate_id {
+                    assert!(batch.contains(other_file));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn topological_puts_dependencies_first() {
+        let (db, files) = test_files();
+
+        let batches = schedule(
+            &db,
+            files.clone(),
+            NonZero::new(1).unwrap(),
+            SchedulingStrategy::Topological,
+        );
+        assert_eq!(
+            paths(&db, &batches),
+            [[
+                "/b/dep/src/lib.cairo",
+                "/c/dep/src/lib.cairo",
+                "/c/dep/src/x.cairo",
+                "/a/hello/src/a.cairo",
+                "/a/hello/src/lib.cairo",
+                "/d/world/src/lib.cairo",
+            ]]
+        );
+
+        // The order is then dealt to workers in turns.
+        let batches = schedule(
+            &db,
+            files,
+            NonZero::new(2).unwrap(),
+            SchedulingStrategy::Topological,
+        );
+        assert_eq!(
+            paths(&db, &batches),
+            [
+                [
+                    "/b/dep/src/lib.cairo",
+                    "/c/dep/src/x.cairo",
+                    "/a/hello/src/lib.cairo",
+                ],
+                [
+                    "/c/dep/src/lib.cairo",
+                    "/a/hello/src/a.cairo",
+                    "/d/world/src/lib.cairo",
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn largest_first_balances_batch_sizes() {
+        let (db, files) = test_files();
+
+        let totals = |n: usize| -> Vec<usize> {
+            schedule(
+                &db,
+                files.clone(),
+                NonZero::new(n).unwrap(),
+                SchedulingStrategy::LargestFirst,
+            )
+            .iter()
+            .map(|batch| batch.iter().map(|file| file_size(&db, *file)).sum())
+            .collect()
+        };
+
+        // Sizes are 50, 50, 30, 20, 10 and 5. Files sorted by path and dealt in turns would give
+        // 80, 15 and 70 to three workers.
+        assert_eq!(totals(2), [85, 80]);
+        assert_eq!(totals(3), [60, 55, 50]);
+
+        let batches = schedule(
+            &db,
+            files,
+            NonZero::new(2).unwrap(),
+            SchedulingStrategy::LargestFirst,
+        );
+        assert_eq!(
+            paths(&db, &batches),
+            [
+                [
+                    "/b/dep/src/lib.cairo",
+                    "/a/hello/src/a.cairo",
+                    "/c/dep/src/x.cairo",
+                ],
+                [
+                    "/c/dep/src/lib.cairo",
+                    "/d/world/src/lib.cairo",
+                    "/a/hello/src/lib.cairo",
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{Context, Result, ensure};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Shape of a synthetic Scarb workspace generated by [`generate_workspace`].
+#[derive(Debug, Clone)]
+pub struct WorkspaceShape {
+    /// Number of member crates.
+    pub crates: usize,
+
+    /// Number of top level modules in each crate.
+    pub modules_per_crate: usize,
+
+    /// Number of nested modules in each chain started by a top level module, including it.
+    pub module_depth: usize,
+
+    /// Number of following crates each crate depends on.
+    pub dependencies_per_crate: usize,
+
+    /// Whether modules should contain generic traits, impls and functions.
+    pub generics: bool,
+
+    /// Whether each crate should contain a Starknet contract.
+    pub starknet_contracts: bool,
+
+    /// Number of semantic errors injected into the workspace, spread over all modules.
+    pub errors: usize,
+}
+
+/// Writes a synthetic Scarb workspace of the given `shape` into the directory under `path`.
+///
+/// Crates are named `crate_0`, `crate_1`, and so on. Each crate depends on the crates directly
+/// following it, so the dependency graph is acyclic, with the first crate transitively depending
+/// on the most others. Generated code is fully deterministic, so experiments on a generated
+/// workspace are repeatable.
+///
+/// The directory must not contain a `Scarb.toml` yet.
+pub fn generate_workspace(path: &Path, shape: &WorkspaceShape) -> Result<()> {
+    ensure!(shape.crates > 0, "a workspace needs at least one crate");
+    ensure!(shape.module_depth > 0, "module depth must be at least 1");
+    ensure!(
+        shape.errors == 0 || shape.modules_per_crate > 0,
+        "errors can only be injected into modules"
+    );
+    ensure!(
+        !path.join("Scarb.toml").exists(),
+        "a Scarb project already exists in: {}",
+        path.display()
+    );
+
+    write_file(
+        &path.join("Scarb.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )?;
+
+    // Errors are spread over all modules of all crates in a round-robin fashion.
+    let modules_in_crate = shape.modules_per_crate * shape.module_depth;
+    let modules_total = shape.crates * modules_in_crate;
+    let errors_in_module = |global_index: usize| -> Vec<usize> {
+        (global_index..shape.errors)
+            .step_by(modules_total.max(1))
+            .collect()
+    };
+
+    for crate_index in 0..shape.crates {
+        let crate_dir = path.join("crates").join(crate_name(crate_index));
+        let dependencies: Vec<_> = (crate_index + 1..shape.crates)
+            .take(shape.dependencies_per_crate)
+            .collect();
+
+        write_file(
+            &crate_dir.join("Scarb.toml"),
+            &manifest(crate_index, &dependencies, shape),
+        )?;
+        write_file(
+            &crate_dir.join("src/lib.cairo"),
+            &lib_file(&dependencies, shape),
+        )?;
+
+        if shape.starknet_contracts {
+            write_file(&crate_dir.join("src/contract.cairo"), CONTRACT)?;
+        }
+
+        for module_index in 0..shape.modules_per_crate {
+            let mut module_path = crate_dir.join("src").join(format!("m_{module_index}"));
+            for level in 0..shape.module_depth {
+                let global_index =
+                    crate_index * modules_in_crate + module_index * shape.module_depth + level;
+                let has_nested = level + 1 < shape.module_depth;
+
+                write_file(
+                    &module_path.with_extension("cairo"),
+                    &module_file(has_nested, &errors_in_module(global_index), shape),
+                )?;
+                module_path.push("nested");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn crate_name(index: usize) -> String {
+    format!("crate_{index}")
+}
+
+fn manifest(crate_index: usize, dependencies: &[usize], shape: &WorkspaceShape) -> String {
+    let mut manifest = format!(
+        "[package]
+name = \"{}\"
+version = \"0.1.0\"
+edition = \"2024_07\"
+
+[dependencies]
+",
+        crate_name(crate_index)
+    );
+    for dependency in dependencies {
+        let name = crate_name(*dependency);
+        writeln!(manifest, "{name} = {{ path = \"../{name}\" }}").unwrap();
+    }
+
+    if shape.starknet_contracts {
+        manifest.push_str("starknet = \">=2.0.0\"\n\n[[target.starknet-contract]]\n");
+    }
+
+    manifest
+}
+
+fn lib_file(dependencies: &[usize], shape: &WorkspaceShape) -> String {
+    let mut lib = String::new();
+    for module_index in 0..shape.modules_per_crate {
+        writeln!(lib, "pub mod m_{module_index};").unwrap();
+    }
+    if shape.starknet_contracts {
+        lib.push_str("pub mod contract;\n");
+    }
+
+    // Reference all dependencies, so that they are actually analysed.
+    lib.push_str("\npub fn use_dependencies(x: felt252) -> felt252 {\n    let mut result = x;\n");
+    for dependency in dependencies.iter().filter(|_| shape.modules_per_crate > 0) {
+        writeln!(
+            lib,
+            "    result = {}::m_0::compute(result);",
+            crate_name(*dependency)
+        )
+        .unwrap();
+    }
+    lib.push_str("    result\n}\n");
+
+    lib
+}
+
+fn module_file(has_nested: bool, errors: &[usize], shape: &WorkspaceShape) -> String {
+    let mut module = String::new();
+    if has_nested {
+        module.push_str("pub mod nested;\n\n");
+    }
+
+    module.push_str(
+        "#[derive(Drop, Copy, PartialEq)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+pub fn compute(x: felt252) -> felt252 {
+    let point = Point { x: 1, y: 2 };
+    x + (point.x + point.y).into()
+}
+",
+    );
+
+    if shape.generics {
+        module.push_str(GENERICS);
+    }
+
+    for error in errors {
+        writeln!(
+            module,
+            "\npub fn injected_error_{error}() -> felt252 {{\n    missing_{error}\n}}"
+        )
+        .unwrap();
+    }
+
+    module
+}
+
+const GENERICS: &str = "
+pub trait Shape<T> {
+    fn area(self: @T) -> u32;
+}
+
+#[derive(Drop, Copy)]
+pub struct Wrapper<T> {
+    pub value: T,
+}
+
+pub impl WrapperShape<T, +Drop<T>, +Copy<T>> of Shape<Wrapper<T>> {
+    fn area(self: @Wrapper<T>) -> u32 {
+        1
+    }
+}
+
+pub fn duplicate<T, +Drop<T>, +Copy<T>>(value: T) -> Array<T> {
+    let mut result = array![];
+    result.append(value);
+    result.append(value);
+    result
+}
+
+pub fn total_area<T, +Shape<T>, +Drop<T>>(shapes: Span<T>) -> u32 {
+    let mut total = 0;
+    for shape in shapes {
+        total += shape.area();
+    };
+    total
+}
+";
+
+const CONTRACT: &str = "#[starknet::interface]
+pub trait ICounter<TContractState> {
+    fn get(self: @TContractState) -> felt252;
+    fn increase(ref self: TContractState, amount: felt252);
+}
+
+#[starknet::contract]
+pub mod Counter {
+    use starknet::storage::{StoragePointerReadAccess, StoragePointerWriteAccess};
+
+    #[storage]
+    struct Storage {
+        value: felt252,
+    }
+
+    #[abi(embed_v0)]
+    impl CounterImpl of super::ICounter<ContractState> {
+        fn get(self: @ContractState) -> felt252 {
+            self.value.read()
+        }
+
+        fn increase(ref self: ContractState, amount: felt252) {
+            self.value.write(self.value.read() + amount);
+        }
+    }
+}
+";
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("failed to write file: {}", path.display()))
+}
@@ -0,0 +1,122 @@
+use crate::LoadedProject;
+use crate::diagnostics::DiagnosticController;
+use cairo_lang_defs::db::{DefsGroup, PrivModuleDataQuery};
+use cairo_lang_filesystem::ids::CrateId;
+use salsa::Query;
+use salsa::debug::DebugQueryTable;
+use std::collections::HashSet;
+use std::fmt;
+use std::num::NonZero;
+use std::time::Duration;
+
+/// Summary of diagnostics calculation limited to workspace members.
+#[derive(Debug, Clone)]
+pub struct LazyAnalysisReport {
+    /// Wall time of the whole calculation, including applying referenced crates to the db.
+    pub total: Duration,
+
+    /// Number of all crates of the project, whether applied to the db or not.
+    pub crates: usize,
+
+    /// Number of crates belonging to workspace members, which were analysed eagerly.
+    pub workspace_crates: usize,
+
+    /// Number of crates applied to the db by the end of the calculation, including workspace
+    /// crates and `core`.
+    pub loaded_crates: usize,
+
+    /// Number of times diagnostics were calculated before no new crate was referenced.
+    pub rounds: usize,
+}
+
+impl fmt::Display for LazyAnalysisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Diagnostics calculation time: {:.2?}", self.total)?;
+        writeln!(f, "Crates in the project: {}", self.crates)?;
+        writeln!(f, "Workspace crates: {}", self.workspace_crates)?;
+        writeln!(
+            f,
+            "Loaded crates: {} ({} dependencies were never referenced)",
+            self.loaded_crates,
+            self.crates.saturating_sub(self.loaded_crates)
+        )?;
+        writeln!(f, "Calculation rounds: {}", self.rounds)
+    }
+}
+
+/// Calculates diagnostics only for files of crates from workspace members, applying dependency
+/// crates to the db only once they are referenced.
+///
+/// The project is expected to be loaded with [`LoadOptions::lazy_dependencies`], so only
+/// workspace members and `core` are applied up front. Diagnostics are calculated in rounds. After
+/// each round, pending crates whose root module was looked up by the analysis are applied to the
+/// db, and the next round is run, until no new crate is referenced. Salsa reuses everything that
+/// did not depend on the newly applied crates, so later rounds are much cheaper than the first.
+///
+/// The number of loaded crates shows how many crates an on-demand loading in the LS would have to
+/// load.
+///
+/// [`LoadOptions::lazy_dependencies`]: crate::LoadOptions::lazy_dependencies
+pub fn calculate_diagnostics_for_workspace_members(
+    project: &mut LoadedProject,
+    threads_limit: NonZero<usize>,
+) -> (Vec<String>, LazyAnalysisReport) {
+    let workspace_crates: Vec<_> = project
+        .crates
+        .iter()
+        .filter(|cr| cr.is_workspace_member)
+        .map(|cr| cr.crate_id(&project.db))
+        .collect();
+    let crates = project.crates.len() + project.pending_crates.len();
+
+    let mut rounds = 0;
+    let (diagnostics, total) = DiagnosticController::new(threads_limit).timed(|diag_controller| {
+        loop {
+            let diagnostics =
+                diag_controller.calculate_diagnostics_for_crates(&project.db, &workspace_crates);
+            rounds += 1;
+
+            let touched = touched_crates(&project.db);
+            let (referenced, pending): (Vec<_>, Vec<_>) = project
+                .pending_crates
+                .drain(..)
+                .partition(|cr| touched.contains(&cr.crate_id(&project.db)));
+            project.pending_crates = pending;
+
+            if referenced.is_empty() {
+                break diagnostics;
+            }
+
+            for cr in &referenced {
+                cr.apply(&mut project.db);
+            }
+            project.crates.extend(referenced);
+        }
+    });
+
+    let report = LazyAnalysisReport {
+        total,
+        crates,
+        workspace_crates: workspace_crates.len(),
+        loaded_crates: project.crates.len(),
+        rounds,
+    };
+
+    (diagnostics, report)
+}
+
+/// Returns crates with at least one module for which module data was computed.
+///
+/// Module data is the first thing computed when a module is referenced in any way, so it is a
+/// reliable marker of a crate being used. This holds also for crates with no configuration in the
+/// db: resolving a path through a dependency name asks for data of its root module, which is
+/// memoized as an error.
+fn touched_crates(db: &dyn DefsGroup) -> HashSet<CrateId> {
+    PrivModuleDataQuery
+        .in_db(db)
+        .entries::<Vec<_>>()
+        .into_iter()
+        .filter(|entry| entry.value.is_some())
+        .map(|entry| entry.key.owning_crate(db))
+        .collect()
+}
@@ -1,13 +1,64 @@
+use anyhow::{bail, ensure};
 use cairo_lang_compiler::db::RootDatabase;
-use scarb_metadata::MetadataCommand;
+use cairo_lang_compiler::project::setup_project;
+use cairo_lang_filesystem::db::{CORELIB_CRATE_NAME, FilesGroup};
+use itertools::Itertools;
+use scarb_metadata::Metadata;
 use std::num::NonZero;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::diagnostics::DiagnosticController;
+use crate::memory::{format_peak_rss, peak_rss};
+use crate::project::cache::{assign_cache_files, write_cache_files};
+use crate::project::compatibility::check_scarb_compatibility;
+use crate::project::detached::detached_crate;
+use crate::project::experimental_features::{
+    apply_experimental_feature_overrides, unknown_experimental_features,
+};
 use crate::project::extract_crates;
+use crate::project::json::read_project_json;
+use crate::project::metadata::scarb_metadata;
+use crate::project::overrides::apply_crate_overrides;
+use crate::project::workspaces::merge_workspaces_crates;
 
+pub use crate::baseline::{
+    Baseline, BaselineComparison, BaselineComparisonRow, Baselines, baseline_project_key,
+    machine_fingerprint,
+};
+pub use crate::bench::{BenchReport, Samples, ThreadCountStats, bench_diagnostics};
+pub use crate::compilation_units::{
+    CompilationUnitSummary, CompilationUnitsReport, DiagnosticDifference,
+    calculate_diagnostics_per_compilation_unit,
+};
+pub use crate::diagnostics::{QueryTiming, SchedulingReport, SchedulingStrategy, WorkerStats};
+pub use crate::generate::{WorkspaceShape, generate_workspace};
+pub use crate::lazy::{LazyAnalysisReport, calculate_diagnostics_for_workspace_members};
+pub use crate::memory::{MemoryReport, QueryGroupEntries, memory_report};
+pub use crate::profiling::{QueryProfile, profile_diagnostics};
+pub use crate::project::cache::CacheReport;
+pub use crate::project::crate_model::Crate;
+pub use crate::project::discovery::ProjectManifestPath;
+pub use crate::project::experimental_features::{
+    EXPERIMENTAL_FEATURES, ExperimentalFeatureOverride, UnknownExperimentalFeature,
+};
+pub use crate::project::json::ProjectJson;
+pub use crate::project::metadata::{MetadataError, ScarbMetadataArgs};
+pub use crate::project::overrides::{CrateOverride, CrateOverrideKind};
+pub use crate::project::plugins::BuiltinPlugin;
+pub use crate::reload::{ReloadReport, reload_project};
+pub use crate::warm_up::{WarmUpReport, compare_warm_up};
+
+mod baseline;
+mod bench;
+mod compilation_units;
 mod diagnostics;
+mod generate;
+mod lazy;
+mod memory;
+mod profiling;
 mod project;
+mod reload;
+mod warm_up;
 
 /// Loads a Scarb project with Scarb.toml under `manifest_path`.
 /// This function calls `scarb metadata` and extracts information about the project from it.
@@ -15,20 +66,328 @@ mod project;
 ///
 /// This simulates LS behaviour when opening a cairo file from a Scarb project for the first time.
 pub fn load_scarb_project(manifest_path: PathBuf) -> anyhow::Result<RootDatabase> {
+    Ok(load_scarb_project_with_options(manifest_path, &LoadOptions::default())?.db)
+}
+
+/// A project loaded into a db.
+pub struct LoadedProject {
+    pub db: RootDatabase,
+
+    /// Crates applied to [`LoadedProject::db`].
+    ///
+    /// Empty if the project was not loaded from Scarb metadata.
+    pub crates: Vec<Crate>,
+
+    /// Crates of the project not applied to [`LoadedProject::db`] yet.
+    ///
+    /// Empty unless the project was loaded with [`LoadOptions::lazy_dependencies`].
+    pub pending_crates: Vec<Crate>,
+
+    /// Summary of using crate cache files, `None` unless [`LoadOptions::cache_dir`] was set.
+    pub cache: Option<CacheReport>,
+
+    /// Experimental features enabled in manifests of the project, but unknown to the compiler.
+    pub unknown_experimental_features: Vec<UnknownExperimentalFeature>,
+
+    /// Overrides from [`LoadOptions::experimental_feature_overrides`] which matched no crate.
+    pub unmatched_experimental_feature_overrides: Vec<ExperimentalFeatureOverride>,
+}
+
+/// Loads projects that `paths` belong to into a single db.
+///
+/// Each path can be a manifest, a Cairo file or a directory. Manifests of the projects are found
+/// with [`ProjectManifestPath::discover`].
+///
+/// This simulates LS behaviour when a Cairo file is opened for the first time: LS is not given
+/// a manifest, but has to find one for the opened file.
+pub fn load_project(paths: &[PathBuf], options: &LoadOptions) -> anyhow::Result<LoadedProject> {
+    let DiscoveredProjects {
+        scarb_manifests,
+        cairo_projects,
+        detached_files,
+    } = discover_projects(paths)?;
+
+    match (
+        scarb_manifests.is_empty(),
+        cairo_projects.as_slice(),
+        detached_files.is_empty(),
+    ) {
+        (false, [], true) => load_scarb_workspaces(scarb_manifests, options),
+        (true, [], false) => load_detached_files(&detached_files),
+        (true, [cairo_project], true) => {
+            let project_dir = cairo_project
+                .parent()
+                .expect("manifest path should have a parent");
+
+            Ok(LoadedProject {
+                db: load_cairo_project(project_dir)?,
+                crates: vec![],
+                pending_crates: vec![],
+                cache: None,
+                unknown_experimental_features: vec![],
+                unmatched_experimental_feature_overrides: vec![],
+            })
+        }
+        (_, [_, ..], _) => bail!("a cairo_project.toml project can only be loaded on its own"),
+        _ => bail!("files outside of any project cannot be loaded together with Scarb projects"),
+    }
+}
+
+/// Manifests of projects and loose files found for paths given by the user.
+struct DiscoveredProjects {
+    scarb_manifests: Vec<PathBuf>,
+    cairo_projects: Vec<PathBuf>,
+    detached_files: Vec<PathBuf>,
+}
+
+/// Finds manifests of projects that `paths` belong to, without duplicates.
+fn discover_projects(paths: &[PathBuf]) -> anyhow::Result<DiscoveredProjects> {
+    let mut scarb_manifests = vec![];
+    let mut cairo_projects = vec![];
+    let mut detached_files = vec![];
+
+    for path in paths {
+        let manifest = ProjectManifestPath::discover(path)?;
+        println!("Project manifest for {}: {manifest:?}", path.display());
+
+        match manifest {
+            Some(ProjectManifestPath::Scarb(manifest_path)) => {
+                // Multiple files from the same project can be given.
+                if !scarb_manifests.contains(&manifest_path) {
+                    scarb_manifests.push(manifest_path);
+                }
+            }
+            Some(ProjectManifestPath::CairoProject(manifest_path)) => {
+                if !cairo_projects.contains(&manifest_path) {
+                    cairo_projects.push(manifest_path);
+                }
+            }
+            None if path.is_file() => detached_files.push(path.clone()),
+            None => bail!(
+                "neither Scarb.toml nor cairo_project.toml found for: {}",
+                path.display()
+            ),
+        }
+    }
+
+    Ok(DiscoveredProjects {
+        scarb_manifests,
+        cairo_projects,
+        detached_files,
+    })
+}
+
+/// Loads a project described with a [`ProjectJson`] file under `path`.
+///
+/// Crates are applied exactly as described, with no build tool involved, which makes it possible
+/// to reproduce a project setup without its dependencies being available.
+pub fn load_project_json(path: &Path, options: &LoadOptions) -> anyhow::Result<LoadedProject> {
+    load_crates(read_project_json(path)?, options)
+}
+
+/// Loads Cairo files that do not belong to any project, each as a separate crate depending only on
+/// `core`. The `core` crate is detected from the compiler sources.
+///
+/// This simulates LS behaviour when a loose Cairo file is opened.
+pub fn load_detached_files(files: &[PathBuf]) -> anyhow::Result<LoadedProject> {
+    let mut db = RootDatabase::builder().detect_corelib().build()?;
+
+    let crates = files
+        .iter()
+        .map(|file| detached_crate(file))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    for cr in &crates {
+        cr.apply(&mut db);
+    }
+
+    Ok(LoadedProject {
+        db,
+        crates,
+        pending_crates: vec![],
+        cache: None,
+        unknown_experimental_features: vec![],
+        unmatched_experimental_feature_overrides: vec![],
+    })
+}
+
+/// Options changing how a Scarb project is loaded into the db.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// A directory with crate cache files.
+    ///
+    /// Crates from the standard library and registries are loaded from cache files found in this
+    /// directory instead of being analysed from sources.
+    pub cache_dir: Option<PathBuf>,
+
+    /// Whether to generate cache files in [`LoadOptions::cache_dir`] for crates that could be
+    /// cached but had no cache file yet.
+    pub write_cache: bool,
+
+    /// A directory with snapshots of Scarb metadata.
+    ///
+    /// Metadata is stored there whenever `scarb metadata` succeeds, and the stored snapshot is
+    /// used in case it fails, e.g. because the manifest is being edited.
+    pub metadata_snapshot_dir: Option<PathBuf>,
+
+    /// Experimental features toggled for crates on top of the ones enabled in their manifests.
+    pub experimental_feature_overrides: Vec<ExperimentalFeatureOverride>,
+
+    /// Changes of crate settings applied on top of the ones extracted from Scarb metadata.
+    pub crate_overrides: Vec<CrateOverride>,
+
+    /// Arguments passed through to `scarb metadata`.
+    pub scarb_args: ScarbMetadataArgs,
+
+    /// Whether to apply only workspace members and `core` to the db, leaving other crates in
+    /// [`LoadedProject::pending_crates`] until they are referenced.
+    pub lazy_dependencies: bool,
+}
+
+/// Same as [`load_scarb_project`], but allows customizing the loading process with `options`.
+pub fn load_scarb_project_with_options(
+    manifest_path: PathBuf,
+    options: &LoadOptions,
+) -> anyhow::Result<LoadedProject> {
+    load_scarb_workspaces(vec![manifest_path], options)
+}
+
+/// Loads multiple independent Scarb workspaces into a single db.
+///
+/// Crates shared between workspaces, identified by their name and discriminator, are loaded only
+/// once. If workspaces set up a shared crate differently, a warning is logged and the crate from
+/// the workspace listed first is used.
+///
+/// This simulates LS behaviour when files from several workspaces are open in a single editor
+/// session.
+pub fn load_scarb_workspaces(
+    manifest_paths: Vec<PathBuf>,
+    options: &LoadOptions,
+) -> anyhow::Result<LoadedProject> {
+    let (crates, unknown_experimental_features) =
+        extract_workspaces_crates(manifest_paths, options)?;
+
+    Ok(LoadedProject {
+        unknown_experimental_features,
+        ..load_crates(crates, options)?
+    })
+}
+
+/// Extracts crates from all workspaces under `manifest_paths` and merges them into a single list.
+///
+/// Experimental features unknown to the compiler, found in any of the workspaces, are returned
+/// alongside.
+fn extract_workspaces_crates(
+    manifest_paths: Vec<PathBuf>,
+    options: &LoadOptions,
+) -> anyhow::Result<(Vec<Crate>, Vec<UnknownExperimentalFeature>)> {
+    let mut workspaces = vec![];
+    let mut unknown_features = vec![];
+    for manifest_path in manifest_paths {
+        let metadata = load_scarb_metadata(&manifest_path, options)?;
+        unknown_features.extend(unknown_experimental_features(&metadata));
+        workspaces.push((manifest_path, extract_crates(&metadata)));
+    }
+
+    Ok((
+        merge_workspaces_crates(workspaces),
+        unknown_features.into_iter().unique().collect(),
+    ))
+}
+
+/// Runs `scarb metadata` for `manifest_path` as configured in `options`, and checks whether the
+/// Scarb version is supported.
+fn load_scarb_metadata(manifest_path: &Path, options: &LoadOptions) -> anyhow::Result<Metadata> {
+    let metadata = scarb_metadata(
+        manifest_path,
+        &options.scarb_args,
+        options.metadata_snapshot_dir.as_deref(),
+    )?;
+    check_scarb_compatibility(&metadata)?;
+    Ok(metadata)
+}
+
+/// Applies `crates_to_load` to a new db, after adjusting them according to `options`.
+fn load_crates(crates_to_load: Vec<Crate>, options: &LoadOptions) -> anyhow::Result<LoadedProject> {
     let mut db = RootDatabase::empty();
 
-    let metadata = MetadataCommand::new()
-        .manifest_path(manifest_path)
-        .inherit_stderr()
-        .exec()?;
-    let crates_to_load = extract_crates(&metadata);
+    let (crates_to_load, unmatched_experimental_feature_overrides) =
+        prepare_crates(crates_to_load, options, &db);
+
+    // `core` is referenced by the prelude of every crate, so there is no point in deferring it.
+    let (crates_to_load, pending_crates) = if options.lazy_dependencies {
+        crates_to_load
+            .into_iter()
+            .partition(|cr| cr.is_workspace_member || cr.name == CORELIB_CRATE_NAME)
+    } else {
+        (crates_to_load, vec![])
+    };
 
     // eprintln!("updating crate roots from scarb metadata: {crates_to_load:#?}");
 
-    for cr in crates_to_load {
+    for cr in &crates_to_load {
         cr.apply(&mut db);
     }
 
+    let cache = match &options.cache_dir {
+        Some(cache_dir) => {
+            let now = std::time::Instant::now();
+            let written = options
+                .write_cache
+                .then(|| write_cache_files(&db, &crates_to_load, cache_dir))
+                .transpose()?;
+
+            Some(CacheReport {
+                loaded: crates_to_load
+                    .iter()
+                    .filter(|cr| cr.cache_file.is_some())
+                    .count(),
+                written,
+                generation_time: now.elapsed(),
+            })
+        }
+        None => None,
+    };
+
+    Ok(LoadedProject {
+        db,
+        crates: crates_to_load,
+        pending_crates,
+        cache,
+        unknown_experimental_features: vec![],
+        unmatched_experimental_feature_overrides,
+    })
+}
+
+/// Adjusts crates extracted from Scarb metadata according to `options`, before they are applied
+/// to `db`.
+///
+/// Experimental feature overrides which matched no crate are returned alongside.
+fn prepare_crates(
+    mut crates: Vec<Crate>,
+    options: &LoadOptions,
+    db: &RootDatabase,
+) -> (Vec<Crate>, Vec<ExperimentalFeatureOverride>) {
+    let unmatched_experimental_feature_overrides =
+        apply_experimental_feature_overrides(&mut crates, &options.experimental_feature_overrides);
+    apply_crate_overrides(&mut crates, &options.crate_overrides, &db.cfg_set());
+
+    if let Some(cache_dir) = &options.cache_dir {
+        assign_cache_files(&mut crates, cache_dir);
+    }
+
+    (crates, unmatched_experimental_feature_overrides)
+}
+
+/// Loads a project described by a `cairo_project.toml` under `project_path`, or a single Cairo
+/// file if `project_path` points to one.
+///
+/// Unlike [`load_scarb_project`], this does not require Scarb to be installed, which makes it
+/// suitable for hermetic fixtures. The `core` crate is detected from the compiler sources.
+pub fn load_cairo_project(project_path: &Path) -> anyhow::Result<RootDatabase> {
+    let mut db = RootDatabase::builder().detect_corelib().build()?;
+    setup_project(&mut db, project_path)?;
+
     Ok(db)
 }
 
@@ -41,16 +400,88 @@ pub fn load_scarb_project(manifest_path: PathBuf) -> anyhow::Result<RootDatabase
 /// **NOTE**: in LS additional measures are taken to make sure open files are processed first.
 /// This mechanism was skipped here for clarity.
 /// To learn more, check https://github.com/software-mansion/cairols/blob/7d7611e2369598a68a64d6528519817be71b5dd4/src/lang/diagnostics/mod.rs#L148.
-pub fn calculate_diagnostics_for_all_files(db: &RootDatabase, threads_limit: NonZero<usize>) {
-    let diag_controller = DiagnosticController::new(threads_limit);
+///
+/// Returns all formatted diagnostics.
+pub fn calculate_diagnostics_for_all_files(
+    db: &RootDatabase,
+    threads_limit: NonZero<usize>,
+) -> Vec<String> {
+    let (diagnostics, elapsed) = DiagnosticController::new(threads_limit)
+        .timed(|diag_controller| diag_controller.calculate_diagnostics_for_all_files(db));
+    println!(
+        "Diagnostics calculation time: {elapsed:.2?}, peak RSS: {}",
+        format_peak_rss(peak_rss())
+    );
+
+    diagnostics
+}
+
+/// Same as [`calculate_diagnostics_for_all_files`], but splits files between workers according to
+/// `strategy` and reports how much time each worker spent blocked, waiting for queries computed by
+/// other workers.
+pub fn calculate_diagnostics_with_scheduling(
+    db: &RootDatabase,
+    threads_limit: NonZero<usize>,
+    strategy: SchedulingStrategy,
+) -> (Vec<String>, SchedulingReport) {
+    let (result, _) = DiagnosticController::new(threads_limit)
+        .with_scheduling(strategy)
+        .timed(|diag_controller| diag_controller.calculate_diagnostics_with_scheduling_report(db));
+    result
+}
+
+/// Checks that diagnostics calculated with a single thread are the same as the ones calculated
+/// with `threads_limit` threads.
+///
+/// Each calculation is done on a freshly loaded db, so any difference points to a concurrency bug
+/// in salsa or in the way snapshots are used by the workers.
+pub fn verify_determinism(
+    paths: &[PathBuf],
+    load_options: &LoadOptions,
+    threads_limit: NonZero<usize>,
+) -> anyhow::Result<()> {
+    let single_threaded = {
+        let db = load_project(paths, load_options)?.db;
+        calculate_diagnostics_for_all_files(&db, NonZero::<usize>::MIN)
+    };
 
-    let now = std::time::Instant::now();
+    let multi_threaded = {
+        let db = load_project(paths, load_options)?.db;
+        calculate_diagnostics_for_all_files(&db, threads_limit)
+    };
 
-    diag_controller.calculate_diagnostics_for_all_files(db);
+    // Both results are sorted, so comparing them directly also takes duplicates into account.
+    if single_threaded != multi_threaded {
+        let single_threaded_counts = single_threaded.iter().counts();
+        let multi_threaded_counts = multi_threaded.iter().counts();
+
+        for (entry, count) in &single_threaded_counts {
+            let other_count = multi_threaded_counts
+                .get(entry)
+                .copied()
+                .unwrap_or_default();
+            if *count != other_count {
+                eprintln!(
+                    "reported {count} time(s) with 1 thread and {other_count} time(s) with \
+                     {threads_limit} threads:\n{entry}"
+                );
+            }
+        }
+        for (entry, count) in &multi_threaded_counts {
+            if !single_threaded_counts.contains_key(entry) {
+                eprintln!(
+                    "reported 0 time(s) with 1 thread and {count} time(s) with {threads_limit} \
+                     threads:\n{entry}"
+                );
+            }
+        }
+    }
 
-    // Drop to make sure all threads are joined.
-    drop(diag_controller);
+    ensure!(
+        single_threaded == multi_threaded,
+        "diagnostics calculated with 1 thread differ from the ones calculated with \
+         {threads_limit} threads"
+    );
 
-    let elapsed = now.elapsed();
-    println!("Diagnostics calculation time: {elapsed:.2?}");
+    Ok(())
 }
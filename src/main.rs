@@ -1,33 +1,531 @@
-use clap::Parser;
-use demo_ls::{calculate_diagnostics_for_all_files, load_scarb_project};
+use clap::{Parser, Subcommand};
+use demo_ls::{
+    Baselines, CrateOverride, ExperimentalFeatureOverride, LoadOptions, LoadedProject,
+    ProjectManifestPath, ScarbMetadataArgs, SchedulingStrategy, WorkspaceShape,
+    baseline_project_key, bench_diagnostics, calculate_diagnostics_for_all_files,
+    calculate_diagnostics_for_workspace_members, calculate_diagnostics_per_compilation_unit,
+    calculate_diagnostics_with_scheduling, compare_warm_up, generate_workspace, load_project,
+    load_project_json, memory_report, profile_diagnostics, reload_project, verify_determinism,
+};
+use std::fs;
 use std::num::NonZero;
 use std::path::PathBuf;
 
 #[derive(Parser, Clone, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// A path to a Scarb.toml from the project.
-    pub manifest_path: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Paths to files or directories from the projects, or to their manifests.
+    /// For each path, the nearest Scarb.toml or cairo_project.toml is found,
+    /// a Scarb.toml of a workspace member is replaced with the one of the workspace root,
+    /// and all the projects are loaded into a single db.
+    #[arg(required_unless_present = "project_json")]
+    pub paths: Vec<PathBuf>,
+
+    /// Load crates described in a JSON file instead of discovering projects from paths.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "paths",
+            "verify_determinism",
+            "per_compilation_unit",
+            "reload",
+            "warm_up",
+        ],
+    )]
+    pub project_json: Option<PathBuf>,
 
     /// Maximum number of threads in the thread pool.
     /// A thread pool will spawn `min(threads_limit, available_parallelism)` threads.
     #[arg(long, short, default_value = "4")]
     pub threads_limit: NonZero<usize>,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+
+    /// Calculate diagnostics with 1 thread and with `threads_limit` threads, each on a fresh db,
+    /// and fail if the results differ.
+    #[arg(long, conflicts_with = "lazy_dependencies")]
+    pub verify_determinism: bool,
+
+    /// Report peak RSS and the number of entries memoized in the db after calculating diagnostics.
+    #[arg(long)]
+    pub report_memory: bool,
+
+    /// Time the top-level diagnostics queries called by the workers and print the given number of
+    /// the slowest calls, together with total time and number of calls per query, and busy and
+    /// blocked time per worker.
+    #[arg(long, value_name = "TOP_N", conflicts_with = "lazy_dependencies")]
+    pub profile_queries: Option<usize>,
+
+    /// Load each compilation unit of the Scarb workspace into a separate db, calculate
+    /// diagnostics for each, and report diagnostics that differ between compilation units.
+    #[arg(long, conflicts_with_all = ["profile_queries", "lazy_dependencies", "report_memory"])]
+    pub per_compilation_unit: bool,
+
+    /// Split files between workers with the given strategy and report how much time each worker
+    /// spent blocked on queries computed by other workers.
+    /// One of: round-robin, by-crate, topological, largest-first.
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        conflicts_with_all = ["profile_queries", "lazy_dependencies", "per_compilation_unit"]
+    )]
+    pub scheduling: Option<SchedulingStrategy>,
+
+    /// Comma-separated names of crates, e.g. `core`, to calculate diagnostics for on a single
+    /// thread before spawning workers. Diagnostics are calculated with and without the warm-up
+    /// in alternating order, each time on a fresh db, to report the net effect on the total time.
+    #[arg(
+        long,
+        value_name = "CRATES",
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "verify_determinism",
+            "report_memory",
+            "profile_queries",
+            "lazy_dependencies",
+            "per_compilation_unit",
+            "scheduling",
+            "reload",
+        ]
+    )]
+    pub warm_up: Vec<String>,
+
+    /// Number of calculations with and without the warm-up, each on a freshly loaded db.
+    #[arg(long, default_value = "5", requires = "warm_up")]
+    pub warm_up_samples: NonZero<usize>,
+
+    /// After calculating diagnostics, reload the project into the same db, applying only crates
+    /// that changed, and calculate diagnostics again. Edit Scarb.toml while the first analysis
+    /// runs to see the effect of a real change.
+    #[arg(long, conflicts_with = "lazy_dependencies")]
+    pub reload: bool,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Repeatedly calculate diagnostics for a sweep of thread limits and report statistics.
+    Bench(BenchArgs),
+
+    /// Write a synthetic Scarb workspace of a given shape, for repeatable experiments.
+    Generate(GenerateArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchArgs {
+    /// A path to a Scarb.toml from the project.
+    pub manifest_path: PathBuf,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+
+    /// Comma-separated thread limits to sweep through.
+    /// Speedup and efficiency are calculated relative to the first one.
+    #[arg(long, short, value_delimiter = ',', default_value = "1,2,4,8,16")]
+    pub threads_limits: Vec<NonZero<usize>>,
+
+    /// Number of runs for each thread limit, each on a freshly loaded db.
+    #[arg(long, short, default_value = "5")]
+    pub runs: NonZero<usize>,
+
+    /// Additionally measure recalculation on the same db with a warm cache
+    /// and after a no-op input change.
+    #[arg(long)]
+    pub cache_states: bool,
+
+    /// A path to write the report to in CSV format.
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+
+    /// A JSON file to store median times as baselines in, for this project and machine.
+    /// Baselines of other projects and machines in the file are kept.
+    #[arg(long, value_name = "FILE")]
+    pub save_baseline: Option<PathBuf>,
+
+    /// A JSON file with baselines to compare median times with. Fails if any thread limit is
+    /// slower than its baseline by more than `regression_threshold`, or has no baseline for this
+    /// project and machine.
+    #[arg(long, value_name = "FILE")]
+    pub compare_baseline: Option<PathBuf>,
+
+    /// Maximum allowed slowdown of the median time relative to the baseline, in percent.
+    #[arg(long, value_name = "PERCENT", default_value = "10")]
+    pub regression_threshold: f64,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct GenerateArgs {
+    /// A directory to write the workspace to.
+    pub path: PathBuf,
+
+    /// Number of member crates.
+    #[arg(long, default_value = "10")]
+    pub crates: usize,
+
+    /// Number of top level modules in each crate.
+    #[arg(long, default_value = "10")]
+    pub modules: usize,
+
+    /// Number of nested modules in each chain started by a top level module, including it.
+    #[arg(long, default_value = "1")]
+    pub depth: usize,
+
+    /// Number of crates each crate depends on. Crate `i` depends on crates `i + 1`, `i + 2`, etc.
+    #[arg(long, default_value = "2")]
+    pub dependencies: usize,
+
+    /// Fill modules with generic traits, impls and functions.
+    #[arg(long)]
+    pub generics: bool,
+
+    /// Add a Starknet contract to each crate.
+    #[arg(long)]
+    pub starknet: bool,
+
+    /// Number of semantic errors to inject, spread over all modules.
+    #[arg(long, default_value = "0")]
+    pub errors: usize,
+}
+
+/// Arguments controlling how the project is loaded into the db.
+#[derive(clap::Args, Clone, Debug)]
+pub struct LoadArgs {
+    /// Load only workspace members and `core` up front, schedule diagnostics only for workspace
+    /// members, and load dependency crates once they are referenced. Reports how many crates had
+    /// to be loaded. Not supported by `bench`.
+    #[arg(long)]
+    pub lazy_dependencies: bool,
+
+    /// A directory with crate cache files. Crates from the standard library and registries are
+    /// loaded from cache files found there.
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Generate missing cache files in `cache_dir` after loading the project.
+    #[arg(long, requires = "cache_dir")]
+    pub write_cache: bool,
+
+    /// A directory to store Scarb metadata snapshots in. The last snapshot is used if `scarb
+    /// metadata` fails.
+    #[arg(long)]
+    pub metadata_snapshot_dir: Option<PathBuf>,
+
+    /// Enable an experimental feature for a crate, given as `<crate>=<feature>`. Use
+    /// `<crate>=!<feature>` to disable a feature enabled in the manifest. Can be repeated.
+    #[arg(long = "experimental-feature", value_name = "CRATE=FEATURE")]
+    pub experimental_features: Vec<ExperimentalFeatureOverride>,
+
+    /// Set the edition of a crate, given as `<crate>=<edition>`. Can be repeated.
+    #[arg(
+        long = "edition",
+        value_name = "CRATE=EDITION",
+        value_parser = CrateOverride::parse_edition,
+    )]
+    pub editions: Vec<CrateOverride>,
+
+    /// Add a cfg to a crate, given as `<crate>=<key>:<value>` or `<crate>=<name>`. Can be
+    /// repeated.
+    #[arg(
+        long = "cfg",
+        value_name = "CRATE=CFG",
+        value_parser = CrateOverride::parse_cfg,
+    )]
+    pub cfgs: Vec<CrateOverride>,
+
+    /// Enable a built-in plugin for a crate, given as `<crate>=<plugin>`. Can be repeated.
+    #[arg(
+        long = "enable-plugin",
+        value_name = "CRATE=PLUGIN",
+        value_parser = CrateOverride::parse_enable_plugin,
+    )]
+    pub enabled_plugins: Vec<CrateOverride>,
+
+    /// Disable a built-in plugin for a crate, given as `<crate>=<plugin>`. Can be repeated.
+    #[arg(
+        long = "disable-plugin",
+        value_name = "CRATE=PLUGIN",
+        value_parser = CrateOverride::parse_disable_plugin,
+    )]
+    pub disabled_plugins: Vec<CrateOverride>,
+
+    /// Comma separated list of features to pass to `scarb metadata`.
+    #[arg(short = 'F', long, value_delimiter = ',')]
+    pub features: Vec<String>,
+
+    /// Pass `--all-features` to `scarb metadata`.
+    #[arg(long, conflicts_with = "features")]
+    pub all_features: bool,
+
+    /// Pass `--no-default-features` to `scarb metadata`.
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// Profile to pass to `scarb metadata`.
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Run `scarb metadata` without accessing the network.
+    #[arg(long)]
+    pub offline: bool,
+}
+
+impl LoadArgs {
+    fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            cache_dir: self.cache_dir.clone(),
+            write_cache: self.write_cache,
+            metadata_snapshot_dir: self.metadata_snapshot_dir.clone(),
+            experimental_feature_overrides: self.experimental_features.clone(),
+            crate_overrides: [
+                &self.editions,
+                &self.cfgs,
+                &self.enabled_plugins,
+                &self.disabled_plugins,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+            scarb_args: ScarbMetadataArgs {
+                features: self.features.clone(),
+                all_features: self.all_features,
+                no_default_features: self.no_default_features,
+                profile: self.profile.clone(),
+                offline: self.offline,
+            },
+            lazy_dependencies: self.lazy_dependencies,
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let Args {
-        manifest_path,
+        command,
+        paths,
+        project_json,
         threads_limit,
+        load,
+        verify_determinism: should_verify_determinism,
+        report_memory,
+        profile_queries,
+        per_compilation_unit,
+        scheduling,
+        warm_up,
+        warm_up_samples,
+        reload,
     } = Args::parse();
 
-    let db = load_scarb_project(manifest_path)?;
+    match command {
+        Some(Command::Bench(args)) => return bench(args),
+        Some(Command::Generate(args)) => return generate(args),
+        None => {}
+    }
+
+    let load_options = load.load_options();
+
+    if should_verify_determinism {
+        return verify_determinism(&paths, &load_options, threads_limit);
+    }
+
+    if per_compilation_unit {
+        let [path] = paths.as_slice() else {
+            anyhow::bail!("compilation units can be analysed for a single project only");
+        };
+        let Some(ProjectManifestPath::Scarb(manifest_path)) = ProjectManifestPath::discover(path)?
+        else {
+            anyhow::bail!("compilation units can be analysed for a Scarb project only");
+        };
+
+        let report = calculate_diagnostics_per_compilation_unit(
+            &manifest_path,
+            &load_options,
+            threads_limit,
+        )?;
+        print!("{report}");
+        return Ok(());
+    }
+
+    if !warm_up.is_empty() {
+        let (diagnostics, report) = compare_warm_up(
+            &paths,
+            &load_options,
+            threads_limit,
+            &warm_up,
+            warm_up_samples,
+        )?;
+        for entry in diagnostics {
+            eprint!("{entry}");
+        }
+        print!("{report}");
+        return Ok(());
+    }
+
+    let now = std::time::Instant::now();
+    let mut project = match &project_json {
+        Some(project_json) => load_project_json(project_json, &load_options)?,
+        None => load_project(&paths, &load_options)?,
+    };
+    let cold_load_time = now.elapsed();
+    println!("Project loading time: {cold_load_time:.2?}");
+    if let Some(cache) = &project.cache {
+        print!("{cache}");
+    }
+    print_load_warnings(&project);
 
     // This simulates diagnostics calculation.
     // Mind that in LS scheduling is also done in the background.
-    calculate_diagnostics_for_all_files(&db, threads_limit);
+    let diagnostics = if load_options.lazy_dependencies {
+        let (diagnostics, report) =
+            calculate_diagnostics_for_workspace_members(&mut project, threads_limit);
+        print!("{report}");
+        diagnostics
+    } else if let Some(top_n) = profile_queries {
+        let (diagnostics, profile) = profile_diagnostics(&project.db, threads_limit, top_n);
+        print!("{profile}");
+        diagnostics
+    } else if let Some(strategy) = scheduling {
+        let (diagnostics, report) =
+            calculate_diagnostics_with_scheduling(&project.db, threads_limit, strategy);
+        print!("{report}");
+        diagnostics
+    } else {
+        calculate_diagnostics_for_all_files(&project.db, threads_limit)
+    };
+    for entry in diagnostics {
+        eprint!("{entry}");
+    }
+
+    if reload {
+        let report = reload_project(&mut project, &paths, &load_options)?;
+        print!("{report}");
+        print_load_warnings(&project);
+        println!(
+            "Reload speedup over cold load: {:.2}x",
+            cold_load_time.as_secs_f64() / report.total.as_secs_f64()
+        );
+
+        let diagnostics = calculate_diagnostics_for_all_files(&project.db, threads_limit);
+        println!("Diagnostics after reload: {}", diagnostics.len());
+    }
+
+    if report_memory {
+        print!("{}", memory_report(&project.db));
+    }
 
     // To skip waiting for the salsa drop at the end - annoying.
-    std::mem::forget(db);
+    std::mem::forget(project);
+
+    Ok(())
+}
+
+/// Prints problems with the project setup which did not prevent loading it.
+fn print_load_warnings(project: &LoadedProject) {
+    for feature in &project.unknown_experimental_features {
+        eprintln!("{feature}");
+    }
+    for feature_override in &project.unmatched_experimental_feature_overrides {
+        eprintln!(
+            "experimental feature override for unknown crate: {}",
+            feature_override.crate_name
+        );
+    }
+}
+
+fn bench(
+    BenchArgs {
+        manifest_path,
+        load,
+        threads_limits,
+        runs,
+        cache_states,
+        csv,
+        save_baseline,
+        compare_baseline,
+        regression_threshold,
+    }: BenchArgs,
+) -> anyhow::Result<()> {
+    let load_options = load.load_options();
+    let report = bench_diagnostics(
+        &manifest_path,
+        &load_options,
+        &threads_limits,
+        runs,
+        cache_states,
+    )?;
+
+    print!("{}", report.table());
+
+    if let Some(csv_path) = csv {
+        fs::write(csv_path, report.csv())?;
+    }
+
+    // Reading the key requires running Scarb, so it is done only if baselines are used.
+    let project_key = || baseline_project_key(&manifest_path, &load_options);
+
+    // Compare before saving, so the same file can be used for both.
+    if let Some(baseline_path) = compare_baseline {
+        let comparison = Baselines::read(&baseline_path)?.compare(
+            &project_key()?,
+            &report,
+            regression_threshold,
+        )?;
+        print!("{comparison}");
+
+        let regressions = comparison.regressions();
+        if !regressions.is_empty() {
+            anyhow::bail!(
+                "median diagnostics time regressed by more than {regression_threshold}% for \
+                 thread limits: {}",
+                regressions
+                    .iter()
+                    .map(|row| row.threads_limit.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    if let Some(baseline_path) = save_baseline {
+        let mut baselines = Baselines::read_or_default(&baseline_path)?;
+        baselines.update(&project_key()?, &report);
+        baselines.write(&baseline_path)?;
+    }
+
+    Ok(())
+}
+
+fn generate(
+    GenerateArgs {
+        path,
+        crates,
+        modules,
+        depth,
+        dependencies,
+        generics,
+        starknet,
+        errors,
+    }: GenerateArgs,
+) -> anyhow::Result<()> {
+    let shape = WorkspaceShape {
+        crates,
+        modules_per_crate: modules,
+        module_depth: depth,
+        dependencies_per_crate: dependencies,
+        generics,
+        starknet_contracts: starknet,
+        errors,
+    };
+    generate_workspace(&path, &shape)?;
+
+    println!(
+        "Generated workspace with {crates} crates and {} modules in: {}",
+        crates * modules * depth,
+        path.display()
+    );
 
     Ok(())
 }
@@ -0,0 +1,183 @@
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_defs::db::{
+    CrateModulesQuery, DefsGroup, FileModulesQuery, ModuleFilesQuery, PrivModuleDataQuery,
+};
+use cairo_lang_lowering::db::{
+    FunctionWithBodyLoweringDiagnosticsQuery, LoweringGroup, ModuleLoweringDiagnosticsQuery,
+    PrivFunctionWithBodyMultiLoweringQuery,
+};
+use cairo_lang_parser::db::{
+    FileModuleSyntaxQuery, FileSyntaxDiagnosticsQuery, FileSyntaxQuery, ParserGroup,
+};
+use cairo_lang_semantic::db::{FunctionBodyQuery, ModuleSemanticDiagnosticsQuery, SemanticGroup};
+use salsa::Query;
+use salsa::debug::DebugQueryTable;
+use std::fmt;
+use std::fs;
+
+/// Memory usage of the process and the number of entries memoized in the db.
+#[derive(Debug, Clone)]
+pub struct MemoryReport {
+    /// Peak resident set size of the process in bytes.
+    ///
+    /// `None` if it could not be read, which is always the case outside Linux.
+    pub peak_rss: Option<u64>,
+
+    /// Number of entries stored by the most heavily used queries of each query group.
+    pub query_groups: Vec<QueryGroupEntries>,
+}
+
+/// Number of entries stored by the counted queries from a single query group.
+#[derive(Debug, Clone)]
+pub struct QueryGroupEntries {
+    pub group: &'static str,
+
+    /// Names of the counted queries, which are only a part of the group.
+    pub queries: &'static [&'static str],
+
+    pub entries: usize,
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Peak RSS: {}", format_peak_rss(self.peak_rss))?;
+        for QueryGroupEntries {
+            group,
+            queries,
+            entries,
+        } in &self.query_groups
+        {
+            writeln!(
+                f,
+                "Memoized entries in {group} queries ({}): {entries}",
+                queries.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects a [`MemoryReport`] for the current process and `db`.
+///
+/// Salsa does not expose the memory used by its tables, so the number of stored entries is used as
+/// a proxy. Only the queries which store the largest values (syntax trees, module data, function
+/// bodies and lowerings) are counted.
+pub fn memory_report(db: &RootDatabase) -> MemoryReport {
+    let parser_db: &dyn ParserGroup = db;
+    let defs_db: &dyn DefsGroup = db;
+    let semantic_db: &dyn SemanticGroup = db;
+    let lowering_db: &dyn LoweringGroup = db;
+
+    let query_groups = vec![
+        QueryGroupEntries {
+            group: "parser",
+            queries: &[
+                "file_syntax",
+                "file_module_syntax",
+                "file_syntax_diagnostics",
+            ],
+            entries: FileSyntaxQuery.in_db(parser_db).entries::<EntryCounter>().0
+                + FileModuleSyntaxQuery
+                    .in_db(parser_db)
+                    .entries::<EntryCounter>()
+                    .0
+                + FileSyntaxDiagnosticsQuery
+                    .in_db(parser_db)
+                    .entries::<EntryCounter>()
+                    .0,
+        },
+        QueryGroupEntries {
+            group: "defs",
+            queries: &[
+                "crate_modules",
+                "file_modules",
+                "module_files",
+                "priv_module_data",
+            ],
+            entries: CrateModulesQuery.in_db(defs_db).entries::<EntryCounter>().0
+                + FileModulesQuery.in_db(defs_db).entries::<EntryCounter>().0
+                + ModuleFilesQuery.in_db(defs_db).entries::<EntryCounter>().0
+                + PrivModuleDataQuery
+                    .in_db(defs_db)
+                    .entries::<EntryCounter>()
+                    .0,
+        },
+        QueryGroupEntries {
+            group: "semantic",
+            queries: &["module_semantic_diagnostics", "function_body"],
+            entries: ModuleSemanticDiagnosticsQuery
+                .in_db(semantic_db)
+                .entries::<EntryCounter>()
+                .0
+                + FunctionBodyQuery
+                    .in_db(semantic_db)
+                    .entries::<EntryCounter>()
+                    .0,
+        },
+        QueryGroupEntries {
+            group: "lowering",
+            queries: &[
+                "module_lowering_diagnostics",
+                "function_with_body_lowering_diagnostics",
+                "priv_function_with_body_multi_lowering",
+            ],
+            entries: ModuleLoweringDiagnosticsQuery
+                .in_db(lowering_db)
+                .entries::<EntryCounter>()
+                .0
+                + FunctionWithBodyLoweringDiagnosticsQuery
+                    .in_db(lowering_db)
+                    .entries::<EntryCounter>()
+                    .0
+                + PrivFunctionWithBodyMultiLoweringQuery
+                    .in_db(lowering_db)
+                    .entries::<EntryCounter>()
+                    .0,
+        },
+    ];
+
+    MemoryReport {
+        peak_rss: peak_rss(),
+        query_groups,
+    }
+}
+
+/// Counts entries of a query table without collecting them.
+struct EntryCounter(usize);
+
+impl<T> FromIterator<T> for EntryCounter {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        EntryCounter(iter.into_iter().count())
+    }
+}
+
+/// Formats a peak resident set size returned by [`peak_rss`].
+pub(crate) fn format_peak_rss(peak_rss: Option<u64>) -> String {
+    match peak_rss {
+        Some(peak_rss) => format!("{:.2} MiB", peak_rss as f64 / 1024. / 1024.),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Resets the peak resident set size of the process to the current one, so that [`peak_rss`]
+/// reports the peak reached afterwards.
+///
+/// Does nothing if it is not supported, which is always the case outside Linux.
+pub(crate) fn reset_peak_rss() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+/// Reads the peak resident set size of the process from `/proc/self/status`.
+pub(crate) fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
@@ -0,0 +1,129 @@
+use crate::diagnostics::{DiagnosticController, QueryTiming, WorkerStats};
+use cairo_lang_compiler::db::RootDatabase;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZero;
+use std::time::Duration;
+
+/// Top-level diagnostics query timing of a single diagnostics calculation.
+///
+/// Salsa reports query executions, cache hits and waits for other threads through
+/// `salsa::Database::salsa_event`, which [`RootDatabase`] does not allow to override. Therefore,
+/// only the diagnostics queries called directly by the workers are timed, and each timing includes
+/// both the time of computing their dependencies and the time spent blocked on other workers
+/// computing the same queries. Blocked time of workers is measured separately, from their CPU
+/// times.
+#[derive(Debug, Clone)]
+pub struct QueryProfile {
+    /// Wall time of the whole calculation.
+    pub total: Duration,
+
+    /// All recorded query calls, sorted from the slowest one.
+    pub timings: Vec<QueryTiming>,
+
+    /// Statistics of each worker, ordered by the worker index.
+    pub workers: Vec<WorkerStats>,
+
+    /// Number of the slowest query calls to print.
+    pub top_n: usize,
+}
+
+impl QueryProfile {
+    /// Returns the sum of durations of query calls made by each worker, ordered by worker index.
+    pub fn busy_time_per_worker(&self) -> Vec<(usize, Duration)> {
+        self.timings
+            .iter()
+            .into_group_map_by(|timing| timing.worker)
+            .into_iter()
+            .map(|(worker, timings)| {
+                (
+                    worker,
+                    timings
+                        .iter()
+                        .map(|timing| timing.duration)
+                        .sum::<Duration>(),
+                )
+            })
+            .sorted_by_key(|(worker, _)| *worker)
+            .collect()
+    }
+}
+
+impl fmt::Display for QueryProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Diagnostics calculation time: {:.2?}", self.total)?;
+
+        writeln!(f, "Top {} slowest query calls:", self.top_n)?;
+        for timing in self.timings.iter().take(self.top_n) {
+            writeln!(
+                f,
+                "{:>12.2?}  worker {:<3} {}({})",
+                timing.duration, timing.worker, timing.query, timing.target,
+            )?;
+        }
+
+        writeln!(f, "Total time per query:")?;
+        let totals = self
+            .timings
+            .iter()
+            .into_group_map_by(|timing| timing.query)
+            .into_iter()
+            .map(|(query, timings)| {
+                let total: Duration = timings.iter().map(|timing| timing.duration).sum();
+                (query, timings.len(), total)
+            })
+            .sorted_by_key(|(_, _, total)| std::cmp::Reverse(*total));
+        for (query, calls, total) in totals {
+            writeln!(f, "{total:>12.2?}  {query} ({calls} calls)")?;
+        }
+
+        // Workers that finish early sit idle while others are still blocked on shared queries,
+        // so a large spread between them hints at contention or poor scheduling.
+        writeln!(f, "Busy and blocked time per worker:")?;
+        let busy_times: HashMap<_, _> = self.busy_time_per_worker().into_iter().collect();
+        for stats in &self.workers {
+            let busy_time = busy_times.get(&stats.worker).copied().unwrap_or_default();
+            let blocked = stats
+                .blocked
+                .map_or("-".to_string(), |blocked| format!("{blocked:.2?}"));
+            writeln!(
+                f,
+                "{busy_time:>12.2?} {blocked:>12}  worker {}",
+                stats.worker
+            )?;
+        }
+
+        let blocked: Option<Duration> = self.workers.iter().map(|stats| stats.blocked).sum();
+        match blocked {
+            Some(blocked) => writeln!(f, "Total blocked time: {blocked:.2?}")?,
+            None => writeln!(f, "Total blocked time: unknown")?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Calculates diagnostics for all files, recording the duration of every top-level diagnostics
+/// query called by the workers.
+///
+/// Returns formatted diagnostics together with the profile keeping `top_n` slowest query calls.
+pub fn profile_diagnostics(
+    db: &RootDatabase,
+    threads_limit: NonZero<usize>,
+    top_n: usize,
+) -> (Vec<String>, QueryProfile) {
+    let ((diagnostics, mut timings, workers), total) = DiagnosticController::new(threads_limit)
+        .timed(|diag_controller| diag_controller.calculate_diagnostics_with_query_timings(db));
+
+    timings.sort_by_key(|timing| std::cmp::Reverse(timing.duration));
+
+    let profile = QueryProfile {
+        total,
+        timings,
+        workers,
+        top_n,
+    };
+
+    (diagnostics, profile)
+}
@@ -1,23 +1,35 @@
+use crate::project::cfg::scarb_cfg_set_to_cairo;
 use crate::project::crate_model::Crate;
+use crate::project::experimental_features::experimental_features_config;
 use crate::project::plugins::BuiltinPlugin;
 use anyhow::{Context, Result, bail, ensure};
 use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
-use cairo_lang_filesystem::db::{
-    CORELIB_CRATE_NAME, CrateSettings, DependencySettings, Edition, ExperimentalFeaturesConfig,
-};
+use cairo_lang_filesystem::db::{CORELIB_CRATE_NAME, CrateSettings, DependencySettings, Edition};
 use cairo_lang_utils::OptionHelper;
 use cairo_lang_utils::smol_str::ToSmolStr;
 use itertools::Itertools;
 use scarb_metadata::{
     CompilationUnitCairoPluginMetadata, CompilationUnitComponentDependencyMetadata,
-    CompilationUnitComponentId, Metadata, PackageMetadata,
+    CompilationUnitComponentId, CompilationUnitMetadata, Metadata, PackageMetadata,
 };
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::slice;
 
+pub mod cache;
+pub mod cfg;
+pub mod compatibility;
 pub mod crate_model;
+pub mod detached;
+pub mod discovery;
+pub mod experimental_features;
+pub mod json;
+pub mod metadata;
+pub mod overrides;
 pub mod plugins;
+pub mod stable_hash;
+pub mod workspaces;
 
 /// Extract information about crates that should be loaded to db from Scarb metadata.
 ///
@@ -28,6 +40,30 @@ pub mod plugins;
 /// package, but in reality enabling `no-core` makes sense only for the `core` package itself. To
 /// leave a trace of unreal cases, this function will log a warning if `core` is missing.
 pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
+    extract_crates_from_compilation_units(metadata, &metadata.compilation_units, true)
+}
+
+/// Same as [`extract_crates`], but extracts only crates of `compilation_unit`, with cfg sets
+/// exactly as Scarb sets them for it.
+///
+/// Unlike [`extract_crates`], this does not enable test cfgs for all crates, so code under
+/// `cfg(test)` is analysed only in compilation units of test targets.
+pub fn extract_compilation_unit_crates(
+    metadata: &Metadata,
+    compilation_unit: &CompilationUnitMetadata,
+) -> Vec<Crate> {
+    extract_crates_from_compilation_units(metadata, slice::from_ref(compilation_unit), false)
+}
+
+/// Extracts crates from `compilation_units` of `metadata`.
+///
+/// If `enable_test_cfgs` is set, test cfgs are added to cfg sets of all crates, so that test code
+/// is analysed regardless of the compilation unit.
+fn extract_crates_from_compilation_units(
+    metadata: &Metadata,
+    compilation_units: &[CompilationUnitMetadata],
+    enable_test_cfgs: bool,
+) -> Vec<Crate> {
     // A crate can appear as a component in multiple compilation units.
     // We use a map here to make sure we include dependencies and cfg sets from all CUs.
     // We can keep components with assigned group id separately as they are not affected by this;
@@ -35,7 +71,7 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
     let mut crates_by_component_id: HashMap<CompilationUnitComponentId, Crate> = HashMap::new();
     let mut crates_grouped_by_group_id = HashMap::new();
 
-    for compilation_unit in &metadata.compilation_units {
+    for compilation_unit in compilation_units {
         if compilation_unit.target.kind == "cairo-plugin" {
             continue;
         }
@@ -71,7 +107,8 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
             };
 
             let edition = scarb_package_edition(package, crate_name);
-            let experimental_features = scarb_package_experimental_features(package);
+            let experimental_features =
+                experimental_features_config(&package.experimental_features);
             let version = Some(package.version.clone());
 
             let (root, file_stem) = match validate_and_chop_source_path(
@@ -97,18 +134,20 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
             // For non-workspace members we only add `cfg(target: 'test')` to make sure
             // importing test items tagged with `cfg(test)`
             // from dependencies emits proper diagnostics.
-            let cfg_set = if metadata.workspace.members.contains(&component.package) {
-                cfg_set_from_scarb.map(|cfg_set| {
-                    cfg_set.union(&CfgSet::from_iter([
-                        Cfg::name("test"),
-                        Cfg::kv("target", "test"),
-                    ]))
-                })
-            } else {
+            let is_workspace_member = metadata.workspace.members.contains(&component.package);
+
+            let cfg_set = if !enable_test_cfgs {
                 cfg_set_from_scarb
-                    .map(|cfg_set| cfg_set.union(&CfgSet::from_iter([Cfg::kv("target", "test")])))
-            }
-            .map(|cfg_set| {
+            } else if is_workspace_member {
+                cfg_set_from_scarb.union(&CfgSet::from_iter([
+                    Cfg::name("test"),
+                    Cfg::kv("target", "test"),
+                ]))
+            } else {
+                cfg_set_from_scarb.union(&CfgSet::from_iter([Cfg::kv("target", "test")]))
+            };
+
+            let cfg_set = {
                 let empty_cfg_set = CfgSet::new();
                 let previous_cfg_set = crates_by_component_id
                     .get(&component_id)
@@ -116,7 +155,7 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
                     .unwrap_or(&empty_cfg_set);
 
                 cfg_set.union(previous_cfg_set)
-            });
+            };
 
             let (regular_dependencies, plugin_dependencies) = component
                 .dependencies
@@ -205,7 +244,7 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
                 edition,
                 version,
                 dependencies,
-                cfg_set,
+                cfg_set: Some(cfg_set),
                 experimental_features,
             };
 
@@ -235,8 +274,12 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
                 discriminator: component.discriminator.as_ref().map(ToSmolStr::to_smolstr),
                 root: root.into(),
                 custom_main_file_stems,
+                virtual_root: false,
                 settings,
                 builtin_plugins,
+                is_workspace_member,
+                is_immutable: is_immutable_source(package),
+                cache_file: None,
             };
 
             if compilation_unit.package == component.package {
@@ -302,7 +345,11 @@ pub fn extract_crates(metadata: &Metadata) -> Vec<Crate> {
             settings: first_crate.settings.clone(),
 
             custom_main_file_stems: Some(custom_main_file_stems),
+            virtual_root: false,
             builtin_plugins,
+            is_workspace_member: first_crate.is_workspace_member,
+            is_immutable: first_crate.is_immutable,
+            cache_file: None,
         });
     }
 
@@ -371,36 +418,6 @@ fn scarb_package_edition(package: &PackageMetadata, crate_name: &str) -> Edition
         .unwrap_or_default()
 }
 
-/// Convert a slice of [`scarb_metadata::Cfg`]s to a [`CfgSet`].
-///
-/// The conversion is done the same way as in Scarb (except no panicking):
-/// <https://github.com/software-mansion/scarb/blob/9fe97c8eb8620a1e2103e7f5251c5a9189e75716/scarb/src/ops/metadata.rs#L295-L302>
-fn scarb_cfg_set_to_cairo(cfg_set: &[scarb_metadata::Cfg], crate_name: &str) -> Option<CfgSet> {
-    serde_json::to_value(cfg_set)
-        .and_then(serde_json::from_value)
-        .with_context(|| {
-            format!(
-                "scarb metadata cfg did not convert identically to cairo one for crate: \
-                 {crate_name}"
-            )
-        })
-        .inspect_err(|e| eprintln!("{e:?}"))
-        .ok()
-}
-
-/// Get [`ExperimentalFeaturesConfig`] from [`PackageMetadata`] fields.
-fn scarb_package_experimental_features(package: &PackageMetadata) -> ExperimentalFeaturesConfig {
-    let contains =
-        |feature: &str| -> bool { package.experimental_features.iter().any(|f| f == feature) };
-
-    ExperimentalFeaturesConfig {
-        negative_impls: contains("negative_impls"),
-        associated_item_constraints: contains("associated_item_constraints"),
-        coupons: contains("coupons"),
-        user_defined_inline_macros: contains("user_defined_inline_macros"),
-    }
-}
-
 /// Returns all plugins required by the `core` crate.
 fn plugins_for_corelib() -> Vec<BuiltinPlugin> {
     vec![BuiltinPlugin::CairoTest, BuiltinPlugin::Executable]
@@ -419,6 +436,14 @@ fn plugins_from_dependencies(
         .collect()
 }
 
+/// Checks whether sources of `package` cannot change between runs.
+///
+/// This is the case for the standard library and packages downloaded from a registry.
+fn is_immutable_source(package: &PackageMetadata) -> bool {
+    let source = &package.source.repr;
+    source == "std" || source.starts_with("registry+")
+}
+
 fn is_core(package: &Option<&PackageMetadata>) -> bool {
     package.is_some_and(|p| p.name == CORELIB_CRATE_NAME)
 }
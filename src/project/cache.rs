@@ -0,0 +1,108 @@
+use crate::project::crate_model::Crate;
+use crate::project::stable_hash::stable_hash;
+use anyhow::{Context, Result};
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_lowering::cache::generate_crate_cache;
+use itertools::Itertools;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Extension of crate cache files.
+const CACHE_FILE_EXTENSION: &str = "cairo-cache";
+
+/// Summary of using crate cache files while loading a project.
+#[derive(Debug, Clone)]
+pub struct CacheReport {
+    /// Number of crates loaded from cache files.
+    pub loaded: usize,
+
+    /// Number of cache files written after loading, `None` if writing them was not requested.
+    pub written: Option<usize>,
+
+    /// Time of generating and writing cache files.
+    pub generation_time: Duration,
+}
+
+impl fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Crates loaded from cache files: {}", self.loaded)?;
+        if let Some(written) = self.written {
+            writeln!(
+                f,
+                "Cache files written: {written}, generation time: {:.2?}",
+                self.generation_time
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets [`Crate::cache_file`] of immutable crates to cache files previously written to
+/// `cache_dir`.
+pub fn assign_cache_files(crates: &mut [Crate], cache_dir: &Path) {
+    for cr in crates.iter_mut().filter(|cr| cr.is_immutable) {
+        let cache_file = cache_file_path(cr, cache_dir);
+        if cache_file.is_file() {
+            cr.cache_file = Some(cache_file);
+        }
+    }
+}
+
+/// Generates cache files in `cache_dir` for immutable crates that were not loaded from a cache
+/// file.
+///
+/// Crates must be already applied to `db`.
+/// Returns the number of written cache files.
+pub fn write_cache_files(db: &RootDatabase, crates: &[Crate], cache_dir: &Path) -> Result<usize> {
+    fs::create_dir_all(cache_dir).with_context(|| {
+        format!(
+            "failed to create cache directory: {cache_dir}",
+            cache_dir = cache_dir.display()
+        )
+    })?;
+
+    let mut written = 0;
+    for cr in crates
+        .iter()
+        .filter(|cr| cr.is_immutable && cr.cache_file.is_none())
+    {
+        let Ok(cache) = generate_crate_cache(db, cr.crate_id(db)) else {
+            eprintln!("failed to generate cache for crate: {}", cr.name);
+            continue;
+        };
+
+        let cache_file = cache_file_path(cr, cache_dir);
+        fs::write(&cache_file, &*cache).with_context(|| {
+            format!(
+                "failed to write cache file: {cache_file}",
+                cache_file = cache_file.display()
+            )
+        })?;
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Returns a path of the cache file for `cr` in `cache_dir`.
+///
+/// The file name contains a hash of everything that affects the compilation of the crate apart
+/// from its sources, which are assumed to be immutable.
+/// The hash does not account for the compiler version, so the cache directory has to be cleared
+/// after the compiler is updated.
+fn cache_file_path(cr: &Crate, cache_dir: &Path) -> PathBuf {
+    let hash = stable_hash(&(
+        &cr.discriminator,
+        &cr.root,
+        &cr.custom_main_file_stems,
+        &cr.settings,
+        cr.builtin_plugins.iter().sorted().collect_vec(),
+    ));
+
+    cache_dir.join(format!(
+        "{name}-{hash:016x}.{CACHE_FILE_EXTENSION}",
+        name = cr.name
+    ))
+}
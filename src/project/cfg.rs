@@ -0,0 +1,165 @@
+use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
+use std::fmt;
+
+/// A reason why a Scarb cfg entry could not be converted to a Cairo one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CfgConversionError {
+    /// The name or key of the cfg is empty.
+    EmptyKey,
+
+    /// The name or key of the cfg is not a valid identifier, so no `#[cfg(...)]` attribute could
+    /// ever match it.
+    InvalidKey(String),
+}
+
+impl fmt::Display for CfgConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyKey => write!(f, "cfg name is empty"),
+            Self::InvalidKey(key) => write!(f, "cfg name is not a valid identifier: {key:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CfgConversionError {}
+
+/// Converts a single cfg entry from Scarb metadata to a Cairo [`Cfg`].
+pub(crate) fn scarb_cfg_to_cairo(cfg: &scarb_metadata::Cfg) -> Result<Cfg, CfgConversionError> {
+    let (key, _) = key_and_value(cfg);
+
+    if key.is_empty() {
+        return Err(CfgConversionError::EmptyKey);
+    }
+    if !is_identifier(key) {
+        return Err(CfgConversionError::InvalidKey(key.to_owned()));
+    }
+
+    Ok(to_cairo_unchecked(cfg))
+}
+
+/// Converts a cfg set from Scarb metadata to a Cairo [`CfgSet`].
+///
+/// Entries with an empty name are reported as warnings and skipped, while the rest of the set is
+/// kept. Entries whose name is not an identifier are kept, as Scarb passes them to the compiler
+/// as well, but a warning is reported since no `#[cfg(...)]` attribute can match them.
+pub(crate) fn scarb_cfg_set_to_cairo(cfg_set: &[scarb_metadata::Cfg], crate_name: &str) -> CfgSet {
+    cfg_set
+        .iter()
+        .filter_map(|cfg| match scarb_cfg_to_cairo(cfg) {
+            Ok(converted) => Some(converted),
+            Err(e @ CfgConversionError::InvalidKey(_)) => {
+                eprintln!("cfg {cfg:?} of crate {crate_name} can never be matched: {e}");
+                Some(to_cairo_unchecked(cfg))
+            }
+            Err(e @ CfgConversionError::EmptyKey) => {
+                eprintln!("skipping cfg {cfg:?} of crate {crate_name}: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+fn key_and_value(cfg: &scarb_metadata::Cfg) -> (&str, Option<&str>) {
+    match cfg {
+        scarb_metadata::Cfg::Name(name) => (name.as_str(), None),
+        scarb_metadata::Cfg::KV(key, value) => (key.as_str(), Some(value.as_str())),
+    }
+}
+
+fn to_cairo_unchecked(cfg: &scarb_metadata::Cfg) -> Cfg {
+    match key_and_value(cfg) {
+        (key, None) => Cfg::name(key),
+        (key, Some(value)) => Cfg::kv(key, value),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CfgConversionError, scarb_cfg_set_to_cairo, scarb_cfg_to_cairo};
+    use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
+
+    #[test]
+    fn name_only_cfg() {
+        let cfg = scarb_metadata::Cfg::Name("test".into());
+
+        assert_eq!(scarb_cfg_to_cairo(&cfg), Ok(Cfg::name("test")));
+    }
+
+    #[test]
+    fn key_value_cfg() {
+        let cfg = scarb_metadata::Cfg::KV("target".into(), "lib".into());
+
+        assert_eq!(scarb_cfg_to_cairo(&cfg), Ok(Cfg::kv("target", "lib")));
+    }
+
+    #[test]
+    fn value_is_not_validated() {
+        // Values are string literals in `#[cfg(...)]`, so they can contain anything.
+        let cfg = scarb_metadata::Cfg::KV("feature".into(), "my-feature v2".into());
+
+        assert_eq!(
+            scarb_cfg_to_cairo(&cfg),
+            Ok(Cfg::kv("feature", "my-feature v2"))
+        );
+    }
+
+    #[test]
+    fn empty_key_is_rejected() {
+        assert_eq!(
+            scarb_cfg_to_cairo(&scarb_metadata::Cfg::Name(String::new())),
+            Err(CfgConversionError::EmptyKey)
+        );
+        assert_eq!(
+            scarb_cfg_to_cairo(&scarb_metadata::Cfg::KV(String::new(), "value".into())),
+            Err(CfgConversionError::EmptyKey)
+        );
+    }
+
+    #[test]
+    fn non_identifier_key_is_rejected() {
+        for key in ["my-cfg", "1st", "with space"] {
+            assert_eq!(
+                scarb_cfg_to_cairo(&scarb_metadata::Cfg::Name(key.into())),
+                Err(CfgConversionError::InvalidKey(key.into())),
+                "{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_entries_do_not_drop_the_whole_set() {
+        let cfg_set = [
+            scarb_metadata::Cfg::Name("test".into()),
+            scarb_metadata::Cfg::Name(String::new()),
+            scarb_metadata::Cfg::KV("target".into(), "test".into()),
+        ];
+
+        assert_eq!(
+            scarb_cfg_set_to_cairo(&cfg_set, "hello"),
+            CfgSet::from_iter([Cfg::name("test"), Cfg::kv("target", "test")])
+        );
+    }
+
+    #[test]
+    fn non_identifier_entries_are_kept_in_the_set() {
+        let cfg_set = [
+            scarb_metadata::Cfg::Name("test".into()),
+            scarb_metadata::Cfg::Name("my-cfg".into()),
+            scarb_metadata::Cfg::KV("1st".into(), "value".into()),
+        ];
+
+        assert_eq!(
+            scarb_cfg_set_to_cairo(&cfg_set, "hello"),
+            CfgSet::from_iter([
+                Cfg::name("test"),
+                Cfg::name("my-cfg"),
+                Cfg::kv("1st", "value")
+            ])
+        );
+    }
+}
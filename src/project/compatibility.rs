@@ -0,0 +1,126 @@
+use anyhow::{Result, bail};
+use scarb_metadata::Metadata;
+
+/// Version of the Cairo compiler crates this tool is built with, as `(major, minor)`.
+///
+/// All `cairo-lang-*` crates in `Cargo.toml` are pinned to the same git revision
+/// ([`PINNED_CAIRO_REV`]), which belongs to the Cairo 2.11 release line. Neither the revision nor
+/// the crates expose the release number, so both constants must be bumped together; the
+/// `cairo_rev_matches_manifest` test fails when only `Cargo.toml` is changed.
+const PINNED_CAIRO_VERSION: (u64, u64) = (2, 11);
+
+/// Git revision of the `cairo-lang-*` crates [`PINNED_CAIRO_VERSION`] describes.
+#[cfg(test)]
+const PINNED_CAIRO_REV: &str = "44eadf196a0323cd5f8fdebc309ad6a648c28523";
+
+/// The oldest Scarb version whose metadata contains everything [`extract_crates`] relies on:
+/// component ids, component dependencies, discriminators and `group-id` target params.
+///
+/// Scarb 2.10.0 is the first release which sets all of them; it is unrelated to
+/// [`PINNED_CAIRO_VERSION`], which can be raised without changing this.
+///
+/// [`extract_crates`]: crate::project::extract_crates
+const MIN_SCARB_VERSION: (u64, u64) = (2, 10);
+
+/// Checks whether the Scarb that produced `metadata` can be used with this tool.
+///
+/// Fails if Scarb is too old to produce metadata this tool can interpret, or if it bundles a Cairo
+/// release other than the one this tool is built with: the corelib shipped with Scarb may then
+/// use syntax or features the pinned compiler does not know about, or lack the ones it expects.
+pub fn check_scarb_compatibility(metadata: &Metadata) -> Result<()> {
+    let scarb_version = &metadata.app_version_info.version;
+    let cairo_version = &metadata.app_version_info.cairo.version;
+
+    check_versions(
+        (scarb_version.major, scarb_version.minor),
+        (cairo_version.major, cairo_version.minor),
+    )
+}
+
+/// Checks `(major, minor)` versions of Scarb and of the Cairo release bundled with it.
+fn check_versions(scarb_version: (u64, u64), cairo_version: (u64, u64)) -> Result<()> {
+    let (scarb_major, scarb_minor) = scarb_version;
+    let (cairo_major, cairo_minor) = cairo_version;
+    let (pinned_major, pinned_minor) = PINNED_CAIRO_VERSION;
+
+    if scarb_version < MIN_SCARB_VERSION {
+        bail!(
+            "Scarb {scarb_major}.{scarb_minor} is not supported: metadata produced by Scarb older \
+             than {}.{}.0 lacks component ids and dependencies needed to set up crates; please \
+             upgrade Scarb",
+            MIN_SCARB_VERSION.0,
+            MIN_SCARB_VERSION.1,
+        );
+    }
+
+    if cairo_version != PINNED_CAIRO_VERSION {
+        let relation = if cairo_version > PINNED_CAIRO_VERSION {
+            "newer"
+        } else {
+            "older"
+        };
+        bail!(
+            "Scarb {scarb_major}.{scarb_minor} bundles Cairo {cairo_major}.{cairo_minor}, which \
+             is {relation} than Cairo {pinned_major}.{pinned_minor} used by demo-ls; please use \
+             Scarb bundling Cairo {pinned_major}.{pinned_minor}"
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PINNED_CAIRO_REV, PINNED_CAIRO_VERSION, check_versions};
+
+    #[test]
+    fn matching_versions_are_accepted() {
+        assert!(check_versions((2, 11), PINNED_CAIRO_VERSION).is_ok());
+        assert!(check_versions((2, 10), PINNED_CAIRO_VERSION).is_ok());
+        assert!(check_versions((3, 0), PINNED_CAIRO_VERSION).is_ok());
+    }
+
+    #[test]
+    fn old_scarb_is_rejected() {
+        let error = check_versions((2, 9), PINNED_CAIRO_VERSION).unwrap_err();
+        assert!(error.to_string().contains("Scarb 2.9 is not supported"));
+
+        assert!(check_versions((1, 11), PINNED_CAIRO_VERSION).is_err());
+    }
+
+    #[test]
+    fn other_cairo_releases_are_rejected() {
+        let (major, minor) = PINNED_CAIRO_VERSION;
+
+        let error = check_versions((2, 12), (major, minor + 1)).unwrap_err();
+        assert!(error.to_string().contains("newer"));
+
+        let error = check_versions((2, 10), (major, minor - 1)).unwrap_err();
+        assert!(error.to_string().contains("older"));
+
+        let error = check_versions((3, 0), (major + 1, 0)).unwrap_err();
+        assert!(error.to_string().contains("newer"));
+    }
+
+    #[test]
+    fn cairo_rev_matches_manifest() {
+        let manifest = include_str!("../../Cargo.toml");
+
+        let revs: Vec<_> = manifest
+            .lines()
+            .filter(|line| line.starts_with("cairo-lang-"))
+            .map(|line| {
+                line.split("rev = \"")
+                    .nth(1)
+                    .and_then(|rest| rest.split('"').next())
+                    .unwrap_or_else(|| panic!("cairo-lang dependency without a rev: {line}"))
+            })
+            .collect();
+
+        assert!(!revs.is_empty());
+        assert!(
+            revs.iter().all(|rev| *rev == PINNED_CAIRO_REV),
+            "cairo-lang revision changed, update PINNED_CAIRO_REV and PINNED_CAIRO_VERSION"
+        );
+    }
+}
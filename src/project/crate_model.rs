@@ -5,16 +5,17 @@ use cairo_lang_defs::ids::ModuleId;
 use cairo_lang_filesystem::db::{
     CORELIB_CRATE_NAME, CrateConfiguration, CrateSettings, FilesGroupEx,
 };
-use cairo_lang_filesystem::ids::{CrateId, CrateLongId, Directory};
+use cairo_lang_filesystem::ids::{BlobLongId, CrateId, CrateLongId, Directory, FileId};
 use cairo_lang_semantic::db::PluginSuiteInput;
 use cairo_lang_semantic::inline_macros::get_default_plugin_suite;
 use cairo_lang_utils::Intern;
 use cairo_lang_utils::smol_str::SmolStr;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 
 /// A complete set of information needed to set up a real crate in the analysis database.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Crate {
     /// Crate name.
     pub name: SmolStr,
@@ -33,13 +34,39 @@ pub struct Crate {
     /// Custom stems of crate main files, if it is not `lib.cairo`.
     ///
     /// This is used to generate a virtual lib file for crates without a root `lib.cairo`.
+    #[serde(default)]
     pub custom_main_file_stems: Option<Vec<SmolStr>>,
 
+    /// Whether the crate root is a virtual directory containing only the files named by
+    /// [`Crate::custom_main_file_stems`] and the virtual lib file, instead of [`Crate::root`].
+    ///
+    /// Crates sharing a root directory then have separate virtual lib files, which also do not
+    /// shadow a real `lib.cairo` there. Requires [`Crate::custom_main_file_stems`] to be set.
+    #[serde(default)]
+    pub virtual_root: bool,
+
     /// Crate settings.
     pub settings: CrateSettings,
 
     /// Built-in plugins required by the crate.
+    #[serde(default)]
     pub builtin_plugins: HashSet<BuiltinPlugin>,
+
+    /// Whether the crate belongs to a workspace member package.
+    #[serde(default)]
+    pub is_workspace_member: bool,
+
+    /// Whether sources of the crate cannot change between runs,
+    /// i.e. the crate comes from the standard library or from a registry.
+    ///
+    /// Only such crates can be safely loaded from cache files.
+    #[serde(default)]
+    pub is_immutable: bool,
+
+    /// A path to a file with cached compilation results of the crate, if it should be loaded
+    /// from one.
+    #[serde(default)]
+    pub cache_file: Option<PathBuf>,
 }
 
 impl Crate {
@@ -50,16 +77,21 @@ impl Crate {
             "invariant violation: only the `core` crate should have no discriminator"
         );
 
-        let crate_id = CrateLongId::Real {
-            name: self.name.clone(),
-            discriminator: self.discriminator.clone(),
-        }
-        .intern(db);
+        let crate_id = self.crate_id(db);
+
+        let root = if self.virtual_root {
+            self.virtual_root_directory(db)
+        } else {
+            Directory::Real(self.root.clone())
+        };
 
         let crate_configuration = CrateConfiguration {
-            root: Directory::Real(self.root.clone()),
+            root,
             settings: self.settings.clone(),
-            cache_file: None,
+            cache_file: self
+                .cache_file
+                .as_ref()
+                .map(|cache_file| BlobLongId::OnDisk(cache_file.clone()).intern(db)),
         };
         db.set_crate_config(crate_id, Some(crate_configuration));
 
@@ -78,6 +110,71 @@ impl Crate {
         let interned_plugins = db.intern_plugin_suite(plugins);
         db.set_override_crate_plugins_from_suite(crate_id, interned_plugins);
     }
+
+    /// Removes this crate from the [`RootDatabase`], reverting [`Crate::apply`].
+    ///
+    /// Plugins overridden for the crate are left in place, as there is no way to unset them, but
+    /// they have no effect once the crate has no configuration.
+    pub fn remove(&self, db: &mut RootDatabase) {
+        let crate_id = self.crate_id(db);
+        self.remove_virtual_wrapper_lib(db);
+        db.set_crate_config(crate_id, None);
+    }
+
+    /// Removes the virtual wrapper lib file injected by [`Crate::apply`], if there is one.
+    ///
+    /// Must be called before the configuration of the crate changes, as the configuration
+    /// determines the path of the wrapper.
+    pub fn remove_virtual_wrapper_lib(&self, db: &mut RootDatabase) {
+        if self.custom_main_file_stems.is_some() {
+            let crate_id = self.crate_id(db);
+            let file_id = db.module_main_file(ModuleId::CrateRoot(crate_id)).unwrap();
+            db.override_file_content(file_id, None);
+        }
+    }
+
+    /// Builds the root directory of a crate with [`Crate::virtual_root`] set.
+    ///
+    /// Main files and their module directories are taken from [`Crate::root`], while the virtual
+    /// lib file gets a path of its own, named after the first main file.
+    fn virtual_root_directory(&self, db: &RootDatabase) -> Directory {
+        let file_stems = self
+            .custom_main_file_stems
+            .as_deref()
+            .expect("crate with a virtual root should have custom main file stems");
+
+        let mut files: BTreeMap<SmolStr, FileId> = file_stems
+            .iter()
+            .map(|stem| {
+                let file_name = format!("{stem}.cairo");
+                let file_id = FileId::new(db, self.root.join(&file_name));
+                (file_name.into(), file_id)
+            })
+            .collect();
+        files.insert(
+            "lib.cairo".into(),
+            FileId::new(db, self.root.join(format!("{}.lib.cairo", file_stems[0]))),
+        );
+
+        let dirs = file_stems
+            .iter()
+            .map(|stem| {
+                let dir = Directory::Real(self.root.join(stem.as_str()));
+                (stem.clone(), Box::new(dir))
+            })
+            .collect();
+
+        Directory::Virtual { files, dirs }
+    }
+
+    /// Returns the id of this crate in the [`RootDatabase`].
+    pub fn crate_id(&self, db: &RootDatabase) -> CrateId {
+        CrateLongId::Real {
+            name: self.name.clone(),
+            discriminator: self.discriminator.clone(),
+        }
+        .intern(db)
+    }
 }
 
 /// Generate a wrapper lib file for a compilation unit without a root `lib.cairo`.
@@ -0,0 +1,77 @@
+use crate::project::crate_model::Crate;
+use crate::project::plugins::BuiltinPlugin;
+use crate::project::validate_and_chop_source_path;
+use anyhow::{Result, ensure};
+use cairo_lang_filesystem::db::{CORELIB_CRATE_NAME, CrateSettings, DependencySettings, Edition};
+use std::path::Path;
+
+/// Creates a crate for a Cairo file that does not belong to any project.
+///
+/// The crate gets the file as its only module, next to a virtual wrapper lib file generated when
+/// the crate is applied. The crate root is virtual, so crates of files from the same directory do
+/// not share the wrapper, and a real `lib.cairo` next to the file is left intact. It depends only
+/// on `core` and has the plugins typically used in Cairo code enabled, so that loose snippets are
+/// analysed without noise about unknown attributes.
+///
+/// The file path is used as the discriminator, so crates created for files with the same stem do
+/// not collide.
+pub fn detached_crate(file: &Path) -> Result<Crate> {
+    ensure!(
+        file.extension()
+            .is_some_and(|extension| extension == "cairo"),
+        "not a Cairo file: {}",
+        file.display()
+    );
+
+    let file = file.canonicalize()?;
+    let (root, file_stem) = validate_and_chop_source_path(&file, "detached")?;
+
+    // The file stem names the crate and the module generated for the file, so it must be a valid
+    // identifier.
+    ensure!(
+        file_stem
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !file_stem.starts_with(|c: char| c.is_ascii_digit()),
+        "file stem is not a valid identifier: {}",
+        file.display()
+    );
+
+    // A `lib.cairo` file is a crate main file itself, so it needs no wrapper.
+    let custom_main_file_stems = (file_stem != "lib").then_some(vec![file_stem.into()]);
+    let virtual_root = custom_main_file_stems.is_some();
+
+    let settings = CrateSettings {
+        name: Some(file_stem.into()),
+        edition: Edition::latest(),
+        version: None,
+        dependencies: [(
+            CORELIB_CRATE_NAME.to_string(),
+            DependencySettings {
+                discriminator: None,
+            },
+        )]
+        .into(),
+        cfg_set: None,
+        experimental_features: Default::default(),
+    };
+
+    Ok(Crate {
+        name: file_stem.into(),
+        discriminator: Some(file.to_string_lossy().into()),
+        root: root.into(),
+        custom_main_file_stems,
+        virtual_root,
+        settings,
+        builtin_plugins: [
+            BuiltinPlugin::AssertMacros,
+            BuiltinPlugin::CairoTest,
+            BuiltinPlugin::Executable,
+            BuiltinPlugin::Starknet,
+        ]
+        .into(),
+        is_workspace_member: true,
+        is_immutable: false,
+        cache_file: None,
+    })
+}
@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCARB_MANIFEST_FILE_NAME: &str = "Scarb.toml";
+const CAIRO_PROJECT_FILE_NAME: &str = "cairo_project.toml";
+
+/// A path to a manifest describing a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectManifestPath {
+    /// A path to a `Scarb.toml`.
+    Scarb(PathBuf),
+    /// A path to a `cairo_project.toml`.
+    CairoProject(PathBuf),
+}
+
+impl ProjectManifestPath {
+    /// Finds a manifest of the project that `path` belongs to.
+    ///
+    /// `path` can be a manifest itself, a Cairo file or a directory. Directories starting from the
+    /// one containing `path` are searched upwards, and the first manifest found is returned.
+    /// If a directory contains both manifests, `Scarb.toml` takes precedence.
+    ///
+    /// A `Scarb.toml` of a workspace member is replaced with the manifest of the workspace root,
+    /// see [`find_workspace_root`], so paths from different members of one workspace resolve to
+    /// the same manifest.
+    ///
+    /// Returns `None` if `path` does not belong to any project.
+    pub fn discover(path: &Path) -> Result<Option<Self>> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("failed to resolve path: {}", path.display()))?;
+
+        let start_dir = if path.is_dir() {
+            path.as_path()
+        } else {
+            match path.file_name().and_then(|name| name.to_str()) {
+                Some(SCARB_MANIFEST_FILE_NAME) => {
+                    return Ok(Some(Self::Scarb(find_workspace_root(path.clone())?)));
+                }
+                Some(CAIRO_PROJECT_FILE_NAME) => return Ok(Some(Self::CairoProject(path.clone()))),
+                _ => path.parent().unwrap_or(&path),
+            }
+        };
+
+        for dir in start_dir.ancestors() {
+            let scarb_manifest = dir.join(SCARB_MANIFEST_FILE_NAME);
+            if scarb_manifest.is_file() {
+                return Ok(Some(Self::Scarb(find_workspace_root(scarb_manifest)?)));
+            }
+
+            let cairo_project = dir.join(CAIRO_PROJECT_FILE_NAME);
+            if cairo_project.is_file() {
+                return Ok(Some(Self::CairoProject(cairo_project)));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The part of a `Scarb.toml` needed to find the workspace root.
+#[derive(Debug, Deserialize)]
+struct ScarbManifest {
+    workspace: Option<WorkspaceManifest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceManifest {
+    #[serde(default)]
+    members: Vec<String>,
+}
+
+impl ScarbManifest {
+    fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read manifest: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("failed to parse manifest: {}", path.display()))
+    }
+}
+
+/// Returns the manifest of the workspace `manifest` belongs to.
+///
+/// Directories above the one of `manifest` are searched upwards for a `Scarb.toml` with
+/// a `[workspace]` section whose `members` match the directory of `manifest`, and the first one
+/// found is returned. `manifest` itself is returned if it defines a workspace, or if no workspace
+/// lists it.
+fn find_workspace_root(manifest: PathBuf) -> Result<PathBuf> {
+    if ScarbManifest::read(&manifest)?.workspace.is_some() {
+        return Ok(manifest);
+    }
+
+    let member_dir = manifest
+        .parent()
+        .expect("manifest path should have a parent");
+    for dir in member_dir.ancestors().skip(1) {
+        let workspace_manifest = dir.join(SCARB_MANIFEST_FILE_NAME);
+        if !workspace_manifest.is_file() {
+            continue;
+        }
+
+        let Some(workspace) = ScarbManifest::read(&workspace_manifest)?.workspace else {
+            continue;
+        };
+        let member_path = member_dir
+            .strip_prefix(dir)
+            .expect("ancestor should be a prefix");
+        if workspace
+            .members
+            .iter()
+            .any(|pattern| matches_member_pattern(pattern, member_path))
+        {
+            return Ok(workspace_manifest);
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Checks whether `member_path`, relative to the workspace root, matches a pattern from workspace
+/// `members`. Each component of the pattern can contain `*` wildcards.
+fn matches_member_pattern(pattern: &str, member_path: &Path) -> bool {
+    let pattern_components: Vec<_> = pattern
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    let path_components: Vec<_> = member_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+
+    pattern_components.len() == path_components.len()
+        && pattern_components
+            .iter()
+            .zip(&path_components)
+            .all(|(pattern, name)| matches_wildcard(pattern, name))
+}
+
+/// Checks whether `name` matches `pattern`, in which `*` stands for any sequence of characters.
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| matches_wildcard(rest, &name[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProjectManifestPath, matches_member_pattern};
+    use std::path::{Path, PathBuf};
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/workspace")
+            .canonicalize()
+            .unwrap()
+    }
+
+    #[test]
+    fn members_resolve_to_workspace_root() {
+        let workspace = fixture();
+        let root_manifest = ProjectManifestPath::Scarb(workspace.join("Scarb.toml"));
+
+        for path in [
+            "crates/first/src/lib.cairo",
+            "crates/first/Scarb.toml",
+            "crates/second/src",
+            "crates/second/src/lib.cairo",
+            "Scarb.toml",
+            ".",
+        ] {
+            assert_eq!(
+                ProjectManifestPath::discover(&workspace.join(path)).unwrap(),
+                Some(root_manifest.clone()),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn member_patterns_are_matched_by_component() {
+        assert!(matches_member_pattern(
+            "crates/*",
+            Path::new("crates/first")
+        ));
+        assert!(matches_member_pattern(
+            "./crates/f*t",
+            Path::new("crates/first")
+        ));
+        assert!(matches_member_pattern("first", Path::new("first")));
+        assert!(!matches_member_pattern(
+            "crates/*",
+            Path::new("crates/first/nested")
+        ));
+        assert!(!matches_member_pattern(
+            "crates/*",
+            Path::new("other/first")
+        ));
+        assert!(!matches_member_pattern(
+            "crates/s*",
+            Path::new("crates/first")
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::project::crate_model::Crate;
+use anyhow::{Context, bail};
+use cairo_lang_filesystem::db::ExperimentalFeaturesConfig;
+use cairo_lang_utils::smol_str::SmolStr;
+use scarb_metadata::Metadata;
+use std::fmt;
+use std::str::FromStr;
+
+/// Defines [`EXPERIMENTAL_FEATURES`] and [`feature_flag`] from a single list of fields of
+/// [`ExperimentalFeaturesConfig`], whose names are the names of features used in `Scarb.toml`.
+macro_rules! experimental_features {
+    ($($feature:ident),* $(,)?) => {
+        /// Names of all experimental features known to the compiler, as used in `Scarb.toml`.
+        pub const EXPERIMENTAL_FEATURES: &[&str] = &[$(stringify!($feature)),*];
+
+        /// Returns the field of `config` corresponding to the feature named `feature`.
+        fn feature_flag<'a>(
+            config: &'a mut ExperimentalFeaturesConfig,
+            feature: &str,
+        ) -> Option<&'a mut bool> {
+            // Destructure without `..`, so that a new field added to the config fails compilation
+            // here instead of being silently ignored.
+            let ExperimentalFeaturesConfig { $($feature),* } = config;
+
+            match feature {
+                $(stringify!($feature) => Some($feature),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+experimental_features!(
+    negative_impls,
+    associated_item_constraints,
+    coupons,
+    user_defined_inline_macros,
+);
+
+/// An experimental feature listed in the manifest of a package, but unknown to the compiler.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownExperimentalFeature {
+    pub package_name: SmolStr,
+    pub feature: String,
+}
+
+impl fmt::Display for UnknownExperimentalFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown experimental feature {:?} enabled for package {}; known features are: {}",
+            self.feature,
+            self.package_name,
+            EXPERIMENTAL_FEATURES.join(", ")
+        )
+    }
+}
+
+/// Builds [`ExperimentalFeaturesConfig`] with `features` enabled.
+///
+/// Features unknown to the compiler are skipped, they are reported by
+/// [`unknown_experimental_features`] instead.
+pub fn experimental_features_config(features: &[String]) -> ExperimentalFeaturesConfig {
+    let mut config = ExperimentalFeaturesConfig::default();
+
+    for feature in features {
+        if let Some(flag) = feature_flag(&mut config, feature) {
+            *flag = true;
+        }
+    }
+
+    config
+}
+
+/// Returns experimental features enabled in manifests of packages from `metadata`, which are
+/// unknown to the compiler.
+pub fn unknown_experimental_features(metadata: &Metadata) -> Vec<UnknownExperimentalFeature> {
+    metadata
+        .packages
+        .iter()
+        .flat_map(|package| {
+            package
+                .experimental_features
+                .iter()
+                .filter(|feature| feature_flag(&mut Default::default(), feature).is_none())
+                .map(|feature| UnknownExperimentalFeature {
+                    package_name: package.name.as_str().into(),
+                    feature: feature.clone(),
+                })
+        })
+        .collect()
+}
+
+/// An experimental feature toggled for a crate from the command line.
+///
+/// Parsed from `<crate>=<feature>` to enable the feature, or `<crate>=!<feature>` to disable it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExperimentalFeatureOverride {
+    pub crate_name: SmolStr,
+    pub feature: String,
+    pub enabled: bool,
+}
+
+impl FromStr for ExperimentalFeatureOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (crate_name, feature) = s
+            .split_once('=')
+            .context("expected <crate>=<feature> or <crate>=!<feature>")?;
+
+        let (feature, enabled) = match feature.strip_prefix('!') {
+            Some(feature) => (feature, false),
+            None => (feature, true),
+        };
+
+        if feature_flag(&mut Default::default(), feature).is_none() {
+            bail!(
+                "unknown experimental feature {feature:?}; known features are: {}",
+                EXPERIMENTAL_FEATURES.join(", ")
+            );
+        }
+
+        Ok(Self {
+            crate_name: crate_name.into(),
+            feature: feature.into(),
+            enabled,
+        })
+    }
+}
+
+/// Applies `overrides` to experimental features of matching `crates`.
+///
+/// Overrides are matched by crate name, so all crates with the given name are affected regardless
+/// of their discriminators. Returns overrides which matched no crate.
+pub fn apply_experimental_feature_overrides(
+    crates: &mut [Crate],
+    overrides: &[ExperimentalFeatureOverride],
+) -> Vec<ExperimentalFeatureOverride> {
+    let mut unmatched = vec![];
+
+    for feature_override in overrides {
+        let mut matched = false;
+        for cr in crates
+            .iter_mut()
+            .filter(|cr| cr.name == feature_override.crate_name)
+        {
+            let flag = feature_flag(
+                &mut cr.settings.experimental_features,
+                &feature_override.feature,
+            )
+            .expect("experimental feature of an override should be validated when parsing");
+            *flag = feature_override.enabled;
+            matched = true;
+        }
+
+        if !matched {
+            unmatched.push(feature_override.clone());
+        }
+    }
+
+    unmatched
+}
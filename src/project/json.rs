@@ -0,0 +1,47 @@
+use crate::project::crate_model::Crate;
+use anyhow::{Context, Result, ensure};
+use cairo_lang_filesystem::db::CORELIB_CRATE_NAME;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A project described explicitly with a list of crates, independent of any build tool.
+///
+/// Relative paths in crates are resolved against the directory containing the JSON file, so
+/// a project can be moved around together with its sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectJson {
+    pub crates: Vec<Crate>,
+}
+
+/// Reads crates from a [`ProjectJson`] file under `path`.
+pub fn read_project_json(path: &Path) -> Result<Vec<Crate>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("failed to read project JSON: {}", path.display()))?;
+    let ProjectJson { mut crates } = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse project JSON: {}", path.display()))?;
+
+    let base_dir = path
+        .canonicalize()?
+        .parent()
+        .expect("file path should have a parent")
+        .to_path_buf();
+
+    for cr in &mut crates {
+        ensure!(
+            (cr.name == CORELIB_CRATE_NAME) ^ cr.discriminator.is_some(),
+            "only the `core` crate can have no discriminator, found crate {} with discriminator \
+             {:?}",
+            cr.name,
+            cr.discriminator
+        );
+
+        // Joining an absolute path replaces the base, so absolute paths are kept as they are.
+        cr.root = base_dir.join(&cr.root);
+        if let Some(cache_file) = &mut cr.cache_file {
+            *cache_file = base_dir.join(&*cache_file);
+        }
+    }
+
+    Ok(crates)
+}
@@ -0,0 +1,349 @@
+use crate::project::stable_hash::stable_hash;
+use anyhow::{Context, Result};
+use scarb_metadata::{Metadata, MetadataCommand, MetadataCommandError};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A classified failure of `scarb metadata`.
+#[derive(Debug)]
+pub enum MetadataError {
+    /// The `scarb` executable could not be found or run.
+    ScarbNotFound(io::Error),
+
+    /// The manifest or one of the manifests in the workspace is malformed.
+    ManifestParse { stderr: String },
+
+    /// Dependencies of the workspace could not be resolved.
+    Resolution { stderr: String },
+
+    /// Scarb is too old to produce metadata in the format understood by this tool.
+    VersionTooOld { stderr: String },
+
+    /// Any other failure.
+    Other(MetadataCommandError),
+}
+
+impl MetadataError {
+    fn classify(error: MetadataCommandError) -> Self {
+        // Substrings of Scarb error messages identifying the cause of the failure.
+        const MANIFEST_PARSE_MARKERS: &[&str] = &["failed to parse manifest"];
+        const RESOLUTION_MARKERS: &[&str] = &[
+            "version solving failed",
+            "cannot find package",
+            "cannot get dependencies",
+            "failed to lookup for",
+            "failed to resolve",
+        ];
+        // Old Scarb versions do not know the `--format-version` argument passed by
+        // `MetadataCommand`.
+        const VERSION_TOO_OLD_MARKERS: &[&str] = &["format-version"];
+
+        let stderr = match error {
+            MetadataCommandError::Io(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Self::ScarbNotFound(e);
+            }
+            MetadataCommandError::ScarbError { ref stderr, .. } => stderr.clone(),
+            error => return Self::Other(error),
+        };
+
+        let contains_any = |markers: &[&str]| markers.iter().any(|m| stderr.contains(m));
+
+        if contains_any(MANIFEST_PARSE_MARKERS) {
+            Self::ManifestParse { stderr }
+        } else if contains_any(RESOLUTION_MARKERS) {
+            Self::Resolution { stderr }
+        } else if contains_any(VERSION_TOO_OLD_MARKERS) {
+            Self::VersionTooOld { stderr }
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ScarbNotFound(e) => write!(f, "scarb executable not found: {e}"),
+            Self::ManifestParse { stderr } => {
+                write!(f, "failed to parse Scarb manifest:\n{stderr}")
+            }
+            Self::Resolution { stderr } => {
+                write!(
+                    f,
+                    "failed to resolve dependencies of Scarb workspace:\n{stderr}"
+                )
+            }
+            Self::VersionTooOld { stderr } => {
+                write!(f, "Scarb version is too old to produce metadata:\n{stderr}")
+            }
+            Self::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ScarbNotFound(e) => Some(e),
+            Self::Other(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Arguments passed through to `scarb metadata`, selecting the project configuration to analyse.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+pub struct ScarbMetadataArgs {
+    /// Features of workspace members to enable.
+    pub features: Vec<String>,
+
+    /// Whether to enable all features of workspace members.
+    pub all_features: bool,
+
+    /// Whether to disable the default features of workspace members.
+    pub no_default_features: bool,
+
+    /// Profile to use instead of the default one.
+    pub profile: Option<String>,
+
+    /// Whether to prevent Scarb from accessing the network.
+    pub offline: bool,
+}
+
+impl ScarbMetadataArgs {
+    /// Applies the arguments to `command`.
+    ///
+    /// `MetadataCommand` does not accept arbitrary arguments, so all options except the profile are
+    /// passed with environment variables read by Scarb in place of the corresponding flags.
+    fn apply(&self, command: &mut MetadataCommand) {
+        if !self.features.is_empty() {
+            command.env("SCARB_FEATURES", self.features.join(","));
+        }
+        if self.all_features {
+            command.env("SCARB_ALL_FEATURES", "true");
+        }
+        if self.no_default_features {
+            command.env("SCARB_NO_DEFAULT_FEATURES", "true");
+        }
+        if let Some(profile) = &self.profile {
+            command.profile(profile);
+        }
+        if self.offline {
+            command.env("SCARB_OFFLINE", "true");
+        }
+    }
+}
+
+/// Runs `scarb metadata` with `args` for the manifest under `manifest_path`.
+///
+/// Scarb stderr is captured, so it can be attached to the returned [`MetadataError`].
+///
+/// If `snapshot_dir` is given, successfully obtained metadata is stored there. When `scarb
+/// metadata` fails later on, the last stored snapshot for the same manifest and arguments is used
+/// instead, so analysis can continue with stale but usable project data.
+pub fn scarb_metadata(
+    manifest_path: &Path,
+    args: &ScarbMetadataArgs,
+    snapshot_dir: Option<&Path>,
+) -> Result<Metadata> {
+    let mut command = MetadataCommand::new();
+    command.manifest_path(manifest_path);
+    args.apply(&mut command);
+
+    let result = command.exec().map_err(MetadataError::classify);
+
+    let Some(snapshot_dir) = snapshot_dir else {
+        return Ok(result?);
+    };
+    let snapshot_path = snapshot_path(manifest_path, args, snapshot_dir);
+
+    match result {
+        Ok(metadata) => {
+            if let Err(e) = write_snapshot(&metadata, &snapshot_path, snapshot_dir) {
+                eprintln!("{e:?}");
+            }
+            Ok(metadata)
+        }
+        Err(error) if snapshot_path.is_file() => {
+            eprintln!(
+                "{error}\nfalling back to metadata snapshot: {}",
+                snapshot_path.display()
+            );
+            read_snapshot(&snapshot_path)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Returns a path of the metadata snapshot for `manifest_path` and `args` in `snapshot_dir`.
+fn snapshot_path(manifest_path: &Path, args: &ScarbMetadataArgs, snapshot_dir: &Path) -> PathBuf {
+    let manifest_path = manifest_path
+        .canonicalize()
+        .unwrap_or_else(|_| manifest_path.to_path_buf());
+
+    let hash = stable_hash(&(manifest_path, args));
+    snapshot_dir.join(format!("metadata-{hash:016x}.json"))
+}
+
+fn write_snapshot(metadata: &Metadata, snapshot_path: &Path, snapshot_dir: &Path) -> Result<()> {
+    fs::create_dir_all(snapshot_dir).with_context(|| {
+        format!(
+            "failed to create metadata snapshot directory: {}",
+            snapshot_dir.display()
+        )
+    })?;
+    fs::write(snapshot_path, serde_json::to_string(metadata)?).with_context(|| {
+        format!(
+            "failed to write metadata snapshot: {}",
+            snapshot_path.display()
+        )
+    })
+}
+
+fn read_snapshot(snapshot_path: &Path) -> Result<Metadata> {
+    let snapshot = fs::read_to_string(snapshot_path).with_context(|| {
+        format!(
+            "failed to read metadata snapshot: {}",
+            snapshot_path.display()
+        )
+    })?;
+    serde_json::from_str(&snapshot).with_context(|| {
+        format!(
+            "failed to deserialize metadata snapshot: {}",
+            snapshot_path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MetadataError, ScarbMetadataArgs, read_snapshot, snapshot_path, write_snapshot};
+    use scarb_metadata::{Metadata, MetadataCommandError};
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    fn scarb_error(stderr: &str) -> MetadataError {
+        MetadataError::classify(MetadataCommandError::ScarbError {
+            stdout: String::new(),
+            stderr: stderr.into(),
+        })
+    }
+
+    #[test]
+    fn scarb_errors_are_classified_by_stderr() {
+        assert!(matches!(
+            scarb_error("error: failed to parse manifest at: /hello/Scarb.toml"),
+            MetadataError::ManifestParse { .. }
+        ));
+        assert!(matches!(
+            scarb_error("error: version solving failed:\nBecause hello depends on world"),
+            MetadataError::Resolution { .. }
+        ));
+        assert!(matches!(
+            scarb_error("error: cannot find package `world`"),
+            MetadataError::Resolution { .. }
+        ));
+        assert!(matches!(
+            scarb_error("error: unexpected argument '--format-version' found"),
+            MetadataError::VersionTooOld { .. }
+        ));
+        assert!(matches!(
+            scarb_error("error: something else went wrong"),
+            MetadataError::Other(MetadataCommandError::ScarbError { .. })
+        ));
+    }
+
+    #[test]
+    fn stderr_is_kept_in_classified_errors() {
+        let MetadataError::ManifestParse { stderr } =
+            scarb_error("error: failed to parse manifest: missing field `name`")
+        else {
+            panic!("expected a manifest parse error");
+        };
+        assert_eq!(
+            stderr,
+            "error: failed to parse manifest: missing field `name`"
+        );
+    }
+
+    #[test]
+    fn io_errors_are_classified_by_kind() {
+        assert!(matches!(
+            MetadataError::classify(io::Error::from(io::ErrorKind::NotFound).into()),
+            MetadataError::ScarbNotFound(_)
+        ));
+        assert!(matches!(
+            MetadataError::classify(io::Error::from(io::ErrorKind::PermissionDenied).into()),
+            MetadataError::Other(MetadataCommandError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn snapshot_path_depends_on_manifest_and_args() {
+        let snapshot_dir = Path::new("/snapshots");
+        let manifest_path = Path::new("/nonexistent/hello/Scarb.toml");
+        let args = ScarbMetadataArgs::default();
+        let other_args = ScarbMetadataArgs {
+            offline: true,
+            ..Default::default()
+        };
+
+        let path = snapshot_path(manifest_path, &args, snapshot_dir);
+
+        assert_eq!(path, snapshot_path(manifest_path, &args, snapshot_dir));
+        assert_ne!(
+            path,
+            snapshot_path(manifest_path, &other_args, snapshot_dir)
+        );
+        assert_ne!(
+            path,
+            snapshot_path(
+                Path::new("/nonexistent/world/Scarb.toml"),
+                &args,
+                snapshot_dir
+            )
+        );
+        assert_eq!(path.parent(), Some(snapshot_dir));
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "app_exe": null,
+                "app_version_info": {
+                    "version": "2.11.4",
+                    "commit_info": null,
+                    "cairo": { "version": "2.11.4", "commit_info": null }
+                },
+                "target_dir": null,
+                "workspace": {
+                    "manifest_path": "/hello/Scarb.toml",
+                    "root": "/hello",
+                    "members": []
+                },
+                "packages": [],
+                "compilation_units": []
+            }"#,
+        )
+        .unwrap();
+        let snapshot_dir =
+            std::env::temp_dir().join(format!("demo-ls-metadata-snapshot-{}", std::process::id()));
+        let snapshot_path = snapshot_path(
+            Path::new("/hello/Scarb.toml"),
+            &ScarbMetadataArgs::default(),
+            &snapshot_dir,
+        );
+
+        write_snapshot(&metadata, &snapshot_path, &snapshot_dir).unwrap();
+        let read = read_snapshot(&snapshot_path);
+        fs::remove_dir_all(&snapshot_dir).unwrap();
+
+        assert_eq!(read.unwrap(), metadata);
+    }
+}
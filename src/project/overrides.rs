@@ -0,0 +1,246 @@
+use crate::project::cfg::scarb_cfg_to_cairo;
+use crate::project::crate_model::Crate;
+use crate::project::plugins::BuiltinPlugin;
+use anyhow::{Context, Result};
+use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
+use cairo_lang_filesystem::db::Edition;
+use cairo_lang_utils::smol_str::SmolStr;
+
+/// A change of crate settings requested from the command line, applied on top of the settings
+/// extracted from Scarb metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateOverride {
+    pub crate_name: SmolStr,
+    pub kind: CrateOverrideKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrateOverrideKind {
+    /// Replaces the edition of the crate.
+    Edition(Edition),
+    /// Adds a cfg to the cfg set of the crate.
+    Cfg(Cfg),
+    /// Adds a built-in plugin to the crate.
+    EnablePlugin(BuiltinPlugin),
+    /// Removes a built-in plugin from the crate.
+    DisablePlugin(BuiltinPlugin),
+}
+
+impl CrateOverride {
+    /// Parses `<crate>=<edition>`, e.g. `hello=2023_10`.
+    pub fn parse_edition(s: &str) -> Result<Self> {
+        parse_with(s, |edition| {
+            let edition = serde_json::from_value(edition.into())
+                .with_context(|| format!("unknown edition: {edition}"))?;
+            Ok(CrateOverrideKind::Edition(edition))
+        })
+    }
+
+    /// Parses `<crate>=<key>:<value>` or `<crate>=<name>`, e.g. `hello=target:test`.
+    pub fn parse_cfg(s: &str) -> Result<Self> {
+        parse_with(s, |cfg| {
+            let cfg = match cfg.split_once(':') {
+                Some((key, value)) => scarb_metadata::Cfg::KV(key.into(), value.into()),
+                None => scarb_metadata::Cfg::Name(cfg.into()),
+            };
+            Ok(CrateOverrideKind::Cfg(scarb_cfg_to_cairo(&cfg)?))
+        })
+    }
+
+    /// Parses `<crate>=<plugin>`, e.g. `hello=cairo_test`, as a plugin to enable.
+    pub fn parse_enable_plugin(s: &str) -> Result<Self> {
+        parse_with(s, |plugin| {
+            Ok(CrateOverrideKind::EnablePlugin(parse_plugin(plugin)?))
+        })
+    }
+
+    /// Parses `<crate>=<plugin>`, e.g. `hello=starknet`, as a plugin to disable.
+    pub fn parse_disable_plugin(s: &str) -> Result<Self> {
+        parse_with(s, |plugin| {
+            Ok(CrateOverrideKind::DisablePlugin(parse_plugin(plugin)?))
+        })
+    }
+}
+
+fn parse_with(
+    s: &str,
+    parse_kind: impl FnOnce(&str) -> Result<CrateOverrideKind>,
+) -> Result<CrateOverride> {
+    let (crate_name, value) = s.split_once('=').context("expected <crate>=<value>")?;
+
+    Ok(CrateOverride {
+        crate_name: crate_name.into(),
+        kind: parse_kind(value)?,
+    })
+}
+
+fn parse_plugin(plugin: &str) -> Result<BuiltinPlugin> {
+    BuiltinPlugin::from_package_name(plugin)
+        .with_context(|| format!("unknown built-in plugin: {plugin}"))
+}
+
+/// Applies `overrides` to matching `crates`, in the order they are given.
+///
+/// Overrides are matched by crate name, so all crates with the given name are affected regardless
+/// of their discriminators. A crate with no cfg set of its own uses `global_cfg_set`, so a cfg
+/// added to such a crate is added on top of `global_cfg_set`.
+pub fn apply_crate_overrides(
+    crates: &mut [Crate],
+    overrides: &[CrateOverride],
+    global_cfg_set: &CfgSet,
+) {
+    for CrateOverride { crate_name, kind } in overrides {
+        let mut matched = false;
+        for cr in crates.iter_mut().filter(|cr| &cr.name == crate_name) {
+            match kind {
+                CrateOverrideKind::Edition(edition) => cr.settings.edition = *edition,
+                CrateOverrideKind::Cfg(cfg) => {
+                    cr.settings
+                        .cfg_set
+                        .get_or_insert_with(|| global_cfg_set.clone())
+                        .insert(cfg.clone());
+                }
+                CrateOverrideKind::EnablePlugin(plugin) => {
+                    cr.builtin_plugins.insert(*plugin);
+                }
+                CrateOverrideKind::DisablePlugin(plugin) => {
+                    cr.builtin_plugins.remove(plugin);
+                }
+            }
+            matched = true;
+        }
+
+        if !matched {
+            eprintln!("crate override for unknown crate: {crate_name}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrateOverride, CrateOverrideKind, apply_crate_overrides};
+    use crate::project::crate_model::Crate;
+    use crate::project::plugins::BuiltinPlugin;
+    use cairo_lang_filesystem::cfg::{Cfg, CfgSet};
+    use cairo_lang_filesystem::db::{CrateSettings, Edition};
+    use std::path::PathBuf;
+
+    fn test_crate(name: &str, cfg_set: Option<CfgSet>) -> Crate {
+        Crate {
+            name: name.into(),
+            discriminator: Some(name.into()),
+            root: PathBuf::from("/").join(name),
+            custom_main_file_stems: None,
+            virtual_root: false,
+            settings: CrateSettings {
+                name: Some(name.into()),
+                edition: Edition::V2024_07,
+                version: None,
+                dependencies: Default::default(),
+                cfg_set,
+                experimental_features: Default::default(),
+            },
+            builtin_plugins: [BuiltinPlugin::Starknet].into(),
+            is_workspace_member: true,
+            is_immutable: false,
+            cache_file: None,
+        }
+    }
+
+    #[test]
+    fn overrides_are_parsed() {
+        assert_eq!(
+            CrateOverride::parse_edition("hello=2023_10").unwrap(),
+            CrateOverride {
+                crate_name: "hello".into(),
+                kind: CrateOverrideKind::Edition(Edition::V2023_10),
+            }
+        );
+        assert_eq!(
+            CrateOverride::parse_cfg("hello=target:test").unwrap().kind,
+            CrateOverrideKind::Cfg(Cfg::kv("target", "test"))
+        );
+        assert_eq!(
+            CrateOverride::parse_cfg("hello=test").unwrap().kind,
+            CrateOverrideKind::Cfg(Cfg::name("test"))
+        );
+        assert_eq!(
+            CrateOverride::parse_enable_plugin("hello=cairo_test")
+                .unwrap()
+                .kind,
+            CrateOverrideKind::EnablePlugin(BuiltinPlugin::CairoTest)
+        );
+        assert_eq!(
+            CrateOverride::parse_disable_plugin("hello=starknet")
+                .unwrap()
+                .kind,
+            CrateOverrideKind::DisablePlugin(BuiltinPlugin::Starknet)
+        );
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        assert!(CrateOverride::parse_edition("2023_10").is_err());
+        assert!(CrateOverride::parse_edition("hello=2077_01").is_err());
+        assert!(CrateOverride::parse_cfg("hello=:test").is_err());
+        assert!(CrateOverride::parse_enable_plugin("hello=time_travel").is_err());
+        assert!(CrateOverride::parse_disable_plugin("hello").is_err());
+    }
+
+    #[test]
+    fn overrides_of_unknown_crates_change_nothing() {
+        let mut crates = vec![test_crate("hello", None)];
+        let overrides = [
+            CrateOverride::parse_edition("world=2023_10").unwrap(),
+            CrateOverride::parse_cfg("world=test").unwrap(),
+            CrateOverride::parse_disable_plugin("world=starknet").unwrap(),
+        ];
+
+        apply_crate_overrides(&mut crates, &overrides, &CfgSet::new());
+
+        assert_eq!(crates, vec![test_crate("hello", None)]);
+    }
+
+    #[test]
+    fn cfg_override_keeps_global_cfgs_of_crate_without_cfg_set() {
+        let global_cfg_set = CfgSet::from_iter([Cfg::name("test")]);
+        let mut crates = vec![test_crate("hello", None)];
+
+        apply_crate_overrides(
+            &mut crates,
+            &[CrateOverride::parse_cfg("hello=target:test").unwrap()],
+            &global_cfg_set,
+        );
+
+        assert_eq!(
+            crates[0].settings.cfg_set,
+            Some(CfgSet::from_iter([
+                Cfg::name("test"),
+                Cfg::kv("target", "test")
+            ]))
+        );
+    }
+
+    #[test]
+    fn cfg_override_extends_own_cfg_set_of_crate() {
+        let global_cfg_set = CfgSet::from_iter([Cfg::name("test")]);
+        let mut crates = vec![test_crate(
+            "hello",
+            Some(CfgSet::from_iter([Cfg::kv("target", "lib")])),
+        )];
+
+        apply_crate_overrides(
+            &mut crates,
+            &[CrateOverride::parse_cfg("hello=feature:fast").unwrap()],
+            &global_cfg_set,
+        );
+
+        assert_eq!(
+            crates[0].settings.cfg_set,
+            Some(CfgSet::from_iter([
+                Cfg::kv("target", "lib"),
+                Cfg::kv("feature", "fast")
+            ]))
+        );
+    }
+}
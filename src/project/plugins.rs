@@ -6,9 +6,11 @@ use cairo_lang_syntax::node::ast::ModuleItem;
 use cairo_lang_syntax::node::db::SyntaxGroup;
 use cairo_lang_test_plugin::{test_assert_suite, test_plugin_suite};
 use scarb_metadata::{CompilationUnitCairoPluginMetadata, Metadata};
+use serde::{Deserialize, Serialize};
 
 /// Representation of known built-in plugins available in the Cairo compiler.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BuiltinPlugin {
     AssertMacros,
     Executable,
@@ -47,11 +49,17 @@ impl BuiltinPlugin {
             return None;
         }
 
-        match package_metadata.name.as_str() {
+        Self::from_package_name(&package_metadata.name)
+    }
+
+    /// Returns the plugin provided by the package named `name`, if it is a known one.
+    pub fn from_package_name(name: &str) -> Option<Self> {
+        match name {
             "assert_macros" => Some(Self::AssertMacros),
             "cairo_execute" => Some(Self::Executable),
             "cairo_test" => Some(Self::CairoTest),
             "starknet" => Some(Self::Starknet),
+            "snforge_scarb_plugin" => Some(Self::SnforgeScarbPlugin),
             _ => None,
         }
     }
@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+/// Hashes `value` serialized to JSON with 64-bit FNV-1a.
+///
+/// Unlike [`std::hash::DefaultHasher`], the result does not depend on the Rust release, so it can
+/// be used in names of files persisted between runs.
+pub fn stable_hash(value: &impl Serialize) -> u64 {
+    let json = serde_json::to_vec(value).expect("value should be serializable to JSON");
+    fnv1a(&json)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, stable_hash};
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn stable_hash_depends_only_on_serialized_value() {
+        assert_eq!(stable_hash(&("core", 1)), fnv1a(br#"["core",1]"#));
+        assert_ne!(stable_hash(&("core", 1)), stable_hash(&("core", 2)));
+    }
+}
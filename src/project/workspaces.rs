@@ -0,0 +1,151 @@
+use crate::project::crate_model::Crate;
+use cairo_lang_utils::smol_str::SmolStr;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::{Path, PathBuf};
+
+/// Merges crates extracted from multiple independent workspaces into a single list.
+///
+/// Crates are identified by their name and discriminator, so dependencies shared between
+/// workspaces are loaded only once. If workspaces disagree on how a shared crate should be set up,
+/// a warning is logged and the crate from the workspace that comes first is kept.
+pub fn merge_workspaces_crates(workspaces: Vec<(PathBuf, Vec<Crate>)>) -> Vec<Crate> {
+    // Manifest path of the workspace that provided each crate, used for reporting conflicts.
+    let mut origins: HashMap<(SmolStr, Option<SmolStr>), (PathBuf, usize)> = HashMap::new();
+    let mut crates: Vec<Crate> = vec![];
+
+    for (manifest_path, workspace_crates) in workspaces {
+        for cr in workspace_crates {
+            match origins.entry((cr.name.clone(), cr.discriminator.clone())) {
+                Entry::Vacant(entry) => {
+                    entry.insert((manifest_path.clone(), crates.len()));
+                    crates.push(cr);
+                }
+                Entry::Occupied(entry) => {
+                    let (origin_manifest_path, index) = entry.get();
+                    let existing = &mut crates[*index];
+
+                    let differences = differences(existing, &cr);
+                    if !differences.is_empty() {
+                        log_conflict(existing, origin_manifest_path, &manifest_path, &differences);
+                    }
+
+                    // A crate being a member of any workspace should be analysed as one.
+                    existing.is_workspace_member |= cr.is_workspace_member;
+                }
+            }
+        }
+    }
+
+    crates
+}
+
+/// Returns names of fields that make `a` and `b` set up differently in the db.
+fn differences(a: &Crate, b: &Crate) -> Vec<&'static str> {
+    let mut differences = vec![];
+    if a.root != b.root {
+        differences.push("root");
+    }
+    if a.custom_main_file_stems != b.custom_main_file_stems {
+        differences.push("custom main file stems");
+    }
+    if a.virtual_root != b.virtual_root {
+        differences.push("virtual root");
+    }
+    if a.settings.edition != b.settings.edition {
+        differences.push("edition");
+    }
+    if a.settings.version != b.settings.version {
+        differences.push("version");
+    }
+    if a.settings.dependencies != b.settings.dependencies {
+        differences.push("dependencies");
+    }
+    if a.settings.cfg_set != b.settings.cfg_set {
+        differences.push("cfg set");
+    }
+    if a.settings.experimental_features != b.settings.experimental_features {
+        differences.push("experimental features");
+    }
+    if a.builtin_plugins != b.builtin_plugins {
+        differences.push("built-in plugins");
+    }
+    differences
+}
+
+fn log_conflict(
+    cr: &Crate,
+    kept_manifest_path: &Path,
+    skipped_manifest_path: &Path,
+    differences: &[&str],
+) {
+    eprintln!(
+        "crate {name} with discriminator {discriminator:?} differs between workspaces {kept} and \
+         {skipped} in: {differences}; using the one from {kept}",
+        name = cr.name,
+        discriminator = cr.discriminator,
+        kept = kept_manifest_path.display(),
+        skipped = skipped_manifest_path.display(),
+        differences = differences.join(", "),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_workspaces_crates;
+    use crate::project::crate_model::Crate;
+    use cairo_lang_filesystem::db::{CrateSettings, Edition};
+    use std::path::PathBuf;
+
+    fn test_crate(name: &str, edition: Edition, is_workspace_member: bool) -> Crate {
+        Crate {
+            name: name.into(),
+            discriminator: Some(format!("{name} 1.0.0").into()),
+            root: PathBuf::from("/").join(name).join("src"),
+            custom_main_file_stems: None,
+            virtual_root: false,
+            settings: CrateSettings {
+                name: Some(name.into()),
+                edition,
+                version: None,
+                dependencies: Default::default(),
+                cfg_set: None,
+                experimental_features: Default::default(),
+            },
+            builtin_plugins: Default::default(),
+            is_workspace_member,
+            is_immutable: false,
+            cache_file: None,
+        }
+    }
+
+    #[test]
+    fn shared_crates_are_merged() {
+        let first = test_crate("first", Edition::V2024_07, true);
+        let second = test_crate("second", Edition::V2024_07, true);
+
+        let merged = merge_workspaces_crates(vec![
+            (
+                PathBuf::from("/first/Scarb.toml"),
+                vec![
+                    first.clone(),
+                    test_crate("shared", Edition::V2024_07, false),
+                ],
+            ),
+            (
+                PathBuf::from("/second/Scarb.toml"),
+                vec![
+                    test_crate("shared", Edition::V2023_10, true),
+                    second.clone(),
+                ],
+            ),
+        ]);
+
+        // The crate from the first workspace is kept, but it is a member of the second one.
+        let shared = Crate {
+            is_workspace_member: true,
+            ..test_crate("shared", Edition::V2024_07, false)
+        };
+        assert_eq!(merged, [first, shared, second]);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::project::crate_model::Crate;
+use crate::{
+    LoadOptions, LoadedProject, discover_projects, extract_workspaces_crates, prepare_crates,
+};
+use anyhow::ensure;
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_utils::smol_str::SmolStr;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Summary of reloading a project into an existing db.
+#[derive(Debug, Clone)]
+pub struct ReloadReport {
+    /// Wall time of the whole reload, including `scarb metadata` calls.
+    pub total: Duration,
+
+    /// Number of crates that were not in the db before.
+    pub added: usize,
+
+    /// Number of crates whose setup changed.
+    pub changed: usize,
+
+    /// Number of crates removed from the db.
+    pub removed: usize,
+
+    /// Number of crates left untouched, with all their memoized results preserved.
+    pub unchanged: usize,
+}
+
+impl fmt::Display for ReloadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Project reload time: {:.2?}", self.total)?;
+        writeln!(
+            f,
+            "Crates added: {}, changed: {}, removed: {}, unchanged: {}",
+            self.added, self.changed, self.removed, self.unchanged
+        )
+    }
+}
+
+/// Reloads Scarb projects that `paths` belong to into the db of `project`.
+///
+/// Crates are extracted from Scarb metadata again and compared with the ones previously applied,
+/// identifying them by their name and discriminator. Only new and changed crates are applied to
+/// the db, and crates missing from the new metadata are removed from it. Inputs of unchanged
+/// crates are not touched at all, so salsa can reuse everything memoized for them.
+///
+/// This simulates LS behaviour when `Scarb.toml` of an opened project changes.
+pub fn reload_project(
+    project: &mut LoadedProject,
+    paths: &[PathBuf],
+    options: &LoadOptions,
+) -> anyhow::Result<ReloadReport> {
+    ensure!(
+        project.pending_crates.is_empty(),
+        "projects with crates not applied to the db yet cannot be reloaded"
+    );
+
+    let now = Instant::now();
+
+    let discovered = discover_projects(paths)?;
+    ensure!(
+        discovered.cairo_projects.is_empty() && discovered.detached_files.is_empty(),
+        "only Scarb projects can be reloaded"
+    );
+
+    let (new_crates, unknown_experimental_features) =
+        extract_workspaces_crates(discovered.scarb_manifests, options)?;
+    let (new_crates, unmatched_experimental_feature_overrides) =
+        prepare_crates(new_crates, options, &project.db);
+    project.unknown_experimental_features = unknown_experimental_features;
+    project.unmatched_experimental_feature_overrides = unmatched_experimental_feature_overrides;
+
+    let changes = apply_crate_changes(
+        &mut project.db,
+        std::mem::take(&mut project.crates),
+        &new_crates,
+    );
+    project.crates = new_crates;
+
+    Ok(ReloadReport {
+        total: now.elapsed(),
+        added: changes.added,
+        changed: changes.changed,
+        removed: changes.removed,
+        unchanged: changes.unchanged,
+    })
+}
+
+/// Numbers of crates by the way they were changed by [`apply_crate_changes`].
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CrateChanges {
+    added: usize,
+    changed: usize,
+    removed: usize,
+    unchanged: usize,
+}
+
+/// Replaces `old_crates`, applied to `db` before, with `new_crates`, touching only the crates
+/// which differ between them.
+fn apply_crate_changes(
+    db: &mut RootDatabase,
+    old_crates: Vec<Crate>,
+    new_crates: &[Crate],
+) -> CrateChanges {
+    let mut old_crates: HashMap<(SmolStr, Option<SmolStr>), _> = old_crates
+        .into_iter()
+        .map(|cr| ((cr.name.clone(), cr.discriminator.clone()), cr))
+        .collect();
+
+    let mut changes = CrateChanges::default();
+
+    for cr in new_crates {
+        match old_crates.remove(&(cr.name.clone(), cr.discriminator.clone())) {
+            None => {
+                cr.apply(db);
+                changes.added += 1;
+            }
+            Some(old) if old != *cr => {
+                old.remove_virtual_wrapper_lib(db);
+                cr.apply(db);
+                changes.changed += 1;
+            }
+            Some(_) => changes.unchanged += 1,
+        }
+    }
+
+    changes.removed = old_crates.len();
+    for old in old_crates.values() {
+        old.remove(db);
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrateChanges, apply_crate_changes};
+    use crate::project::crate_model::Crate;
+    use cairo_lang_compiler::db::RootDatabase;
+    use cairo_lang_defs::db::DefsGroup;
+    use cairo_lang_defs::ids::ModuleId;
+    use cairo_lang_filesystem::db::{CrateSettings, Edition, FilesGroup};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    /// Creates a crate with a virtual wrapper lib file, as if it had no `lib.cairo`.
+    fn test_crate(name: &str, edition: Edition) -> Crate {
+        Crate {
+            name: name.into(),
+            discriminator: Some(format!("{name} 1.0.0").into()),
+            root: PathBuf::from("/").join(name).join("src"),
+            custom_main_file_stems: Some(vec![name.into()]),
+            virtual_root: false,
+            settings: CrateSettings {
+                name: Some(name.into()),
+                edition,
+                version: None,
+                dependencies: Default::default(),
+                cfg_set: None,
+                experimental_features: Default::default(),
+            },
+            builtin_plugins: Default::default(),
+            is_workspace_member: true,
+            is_immutable: false,
+            cache_file: None,
+        }
+    }
+
+    #[test]
+    fn only_differing_crates_are_touched() {
+        let mut db = RootDatabase::empty();
+
+        let kept = test_crate("kept", Edition::V2024_07);
+        let changed = test_crate("changed", Edition::V2023_10);
+        let removed = test_crate("removed", Edition::V2024_07);
+        let old_crates = vec![kept.clone(), changed.clone(), removed.clone()];
+        for cr in &old_crates {
+            cr.apply(&mut db);
+        }
+
+        let removed_id = removed.crate_id(&db);
+        let removed_wrapper = db
+            .module_main_file(ModuleId::CrateRoot(removed_id))
+            .unwrap();
+        assert_eq!(
+            db.file_content(removed_wrapper).unwrap().to_string(),
+            "mod removed;"
+        );
+
+        let new_changed = test_crate("changed", Edition::V2024_07);
+        let added = test_crate("added", Edition::V2024_07);
+        let new_crates = vec![kept.clone(), new_changed, added.clone()];
+
+        let changes = apply_crate_changes(&mut db, old_crates, &new_crates);
+        assert_eq!(
+            changes,
+            CrateChanges {
+                added: 1,
+                changed: 1,
+                removed: 1,
+                unchanged: 1,
+            }
+        );
+
+        let expected_crates: HashSet<_> = [&kept, &changed, &added]
+            .into_iter()
+            .map(|cr| cr.crate_id(&db))
+            .collect();
+        assert_eq!(
+            db.crates().into_iter().collect::<HashSet<_>>(),
+            expected_crates
+        );
+
+        let changed_config = db.crate_config(changed.crate_id(&db)).unwrap();
+        assert_eq!(changed_config.settings.edition, Edition::V2024_07);
+        assert!(db.crate_config(added.crate_id(&db)).is_some());
+
+        assert!(db.crate_config(removed_id).is_none());
+        assert!(db.file_content(removed_wrapper).is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::bench::Samples;
+use crate::diagnostics::DiagnosticController;
+use crate::{LoadOptions, load_project};
+use anyhow::ensure;
+use cairo_lang_compiler::db::RootDatabase;
+use cairo_lang_filesystem::db::FilesGroup;
+use cairo_lang_filesystem::ids::CrateId;
+use std::fmt;
+use std::num::NonZero;
+use std::path::PathBuf;
+
+/// Comparison of diagnostics calculation with and without a single-threaded warm-up phase.
+#[derive(Debug, Clone)]
+pub struct WarmUpReport {
+    /// Names of crates analysed in the warm-up phase.
+    pub crates: Vec<String>,
+
+    /// Durations of the warm-up phase alone.
+    pub warm_up: Samples,
+
+    /// Wall times of the whole calculation with the warm-up phase, including it.
+    pub with_warm_up: Samples,
+
+    /// Wall times of the whole calculation without the warm-up phase.
+    pub without_warm_up: Samples,
+}
+
+impl WarmUpReport {
+    /// Returns the relative change of the median total time caused by the warm-up in percent,
+    /// negative when the warm-up made the calculation faster.
+    pub fn change_percent(&self) -> f64 {
+        (self.with_warm_up.median().as_secs_f64() / self.without_warm_up.median().as_secs_f64()
+            - 1.)
+            * 100.
+    }
+}
+
+impl fmt::Display for WarmUpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Warm-up crates: {}", self.crates.join(", "))?;
+        writeln!(f, "Samples: {}", self.with_warm_up.len())?;
+        writeln!(f, "Median warm-up time: {:.2?}", self.warm_up.median())?;
+        writeln!(
+            f,
+            "Median diagnostics calculation time with warm-up: {:.2?} (min {:.2?})",
+            self.with_warm_up.median(),
+            self.with_warm_up.min()
+        )?;
+        writeln!(
+            f,
+            "Median diagnostics calculation time without warm-up: {:.2?} (min {:.2?})",
+            self.without_warm_up.median(),
+            self.without_warm_up.min()
+        )?;
+        writeln!(f, "Net effect of warm-up: {:+.1}%", self.change_percent())
+    }
+}
+
+/// Calculates diagnostics `samples` times with a warm-up phase analysing `crates` on a single
+/// thread before spawning workers, and as many times without it, each on a freshly loaded db.
+///
+/// The order of the two calculations alternates between samples, so that neither of them
+/// systematically benefits from caches warmed up by the other one.
+///
+/// Returns diagnostics, which are required to be the same in all calculations, together with
+/// a report of the net effect of the warm-up on the total time.
+pub fn compare_warm_up(
+    paths: &[PathBuf],
+    load_options: &LoadOptions,
+    threads_limit: NonZero<usize>,
+    crates: &[String],
+    samples: NonZero<usize>,
+) -> anyhow::Result<(Vec<String>, WarmUpReport)> {
+    let mut report = WarmUpReport {
+        crates: crates.to_vec(),
+        warm_up: Samples::default(),
+        with_warm_up: Samples::default(),
+        without_warm_up: Samples::default(),
+    };
+    let mut diagnostics = None;
+
+    for sample in 0..samples.get() {
+        let warm_up_first = sample % 2 == 0;
+
+        for with_warm_up in [warm_up_first, !warm_up_first] {
+            let db = load_project(paths, load_options)?.db;
+
+            let sample_diagnostics = if with_warm_up {
+                let warm_up_crates = find_crates_by_names(&db, crates)?;
+                let ((sample_diagnostics, warm_up), elapsed) =
+                    DiagnosticController::new(threads_limit)
+                        .with_warm_up(warm_up_crates)
+                        .timed(|diag_controller| {
+                            diag_controller.calculate_diagnostics_with_warm_up_time(&db)
+                        });
+                report.warm_up.push(warm_up);
+                report.with_warm_up.push(elapsed);
+                sample_diagnostics
+            } else {
+                let (sample_diagnostics, elapsed) =
+                    DiagnosticController::new(threads_limit).timed(|diag_controller| {
+                        diag_controller.calculate_diagnostics_for_all_files(&db)
+                    });
+                report.without_warm_up.push(elapsed);
+                sample_diagnostics
+            };
+
+            match &diagnostics {
+                None => diagnostics = Some(sample_diagnostics),
+                Some(diagnostics) => ensure!(
+                    *diagnostics == sample_diagnostics,
+                    "diagnostics calculated with a warm-up differ from the ones calculated \
+                     without it"
+                ),
+            }
+        }
+    }
+
+    Ok((diagnostics.unwrap_or_default(), report))
+}
+
+/// Returns all crates in the db named as any of `names`, with any discriminator.
+fn find_crates_by_names(db: &RootDatabase, names: &[String]) -> anyhow::Result<Vec<CrateId>> {
+    let crates: Vec<_> = db
+        .crates()
+        .into_iter()
+        .filter(|crate_id| names.iter().any(|name| *name == crate_id.name(db).as_str()))
+        .collect();
+
+    for name in names {
+        ensure!(
+            crates
+                .iter()
+                .any(|crate_id| *name == crate_id.name(db).as_str()),
+            "warm-up crate not found in the project: {name}"
+        );
+    }
+
+    Ok(crates)
+}
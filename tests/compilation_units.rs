@@ -0,0 +1,35 @@
+use demo_ls::{LoadOptions, calculate_diagnostics_per_compilation_unit};
+use std::num::NonZero;
+use std::path::Path;
+
+#[test]
+fn cfg_test_code_is_analysed_only_in_test_compilation_units() {
+    let manifest_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compilation_units/Scarb.toml");
+
+    let report = calculate_diagnostics_per_compilation_unit(
+        &manifest_path,
+        &LoadOptions::default(),
+        NonZero::new(2).unwrap(),
+    )
+    .unwrap();
+
+    let [difference] = report.differences.as_slice() else {
+        panic!("expected exactly one difference between compilation units:\n{report}");
+    };
+    assert_eq!(difference.crate_name, "cfg_units");
+    assert!(
+        difference.diagnostic.contains("missing_in_tests"),
+        "unexpected diagnostic: {}",
+        difference.diagnostic
+    );
+
+    // The item exists only under `cfg(test)`, so only test targets can report it.
+    for unit in &report.units {
+        if unit.target.starts_with("test ") {
+            assert!(difference.present_in.contains(&unit.id), "{report}");
+        } else {
+            assert!(difference.missing_in.contains(&unit.id), "{report}");
+        }
+    }
+}
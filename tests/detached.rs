@@ -0,0 +1,37 @@
+//! Tests of loading Cairo files that do not belong to any project.
+
+use demo_ls::{calculate_diagnostics_for_all_files, load_detached_files};
+use std::num::NonZero;
+use std::path::Path;
+
+/// Files from one directory must each be analysed exactly once, without the wrapper lib of one
+/// crate replacing another's or the real `lib.cairo` of the directory.
+#[test]
+fn loose_files_from_one_directory() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/detached")
+        .canonicalize()
+        .unwrap();
+    let files = ["a.cairo", "b.cairo", "lib.cairo"].map(|file| fixture.join(file));
+
+    let project = load_detached_files(&files).unwrap();
+    assert_eq!(project.crates.len(), 3);
+
+    let diagnostics =
+        calculate_diagnostics_for_all_files(&project.db, NonZero::new(4).unwrap()).concat();
+
+    for (file, identifier) in
+        files
+            .iter()
+            .zip(["undefined_in_a", "undefined_in_b", "undefined_in_lib"])
+    {
+        let location = format!("{}:", file.display());
+        assert_eq!(
+            diagnostics.matches(&location).count(),
+            1,
+            "expected one diagnostic in {}:\n{diagnostics}",
+            file.display()
+        );
+        assert_eq!(diagnostics.matches(identifier).count(), 1, "{diagnostics}");
+    }
+}
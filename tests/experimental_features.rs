@@ -0,0 +1,74 @@
+use demo_ls::{
+    EXPERIMENTAL_FEATURES, ExperimentalFeatureOverride, LoadOptions, UnknownExperimentalFeature,
+    load_scarb_project_with_options,
+};
+use std::path::Path;
+
+#[test]
+fn all_known_features_can_be_overridden() {
+    for feature in EXPERIMENTAL_FEATURES {
+        let parsed: ExperimentalFeatureOverride = format!("hello={feature}").parse().unwrap();
+        assert_eq!(
+            parsed,
+            ExperimentalFeatureOverride {
+                crate_name: "hello".into(),
+                feature: feature.to_string(),
+                enabled: true,
+            }
+        );
+    }
+}
+
+#[test]
+fn feature_can_be_disabled() {
+    let parsed: ExperimentalFeatureOverride = "hello=!coupons".parse().unwrap();
+
+    assert!(!parsed.enabled);
+    assert_eq!(parsed.feature, "coupons");
+}
+
+#[test]
+fn unknown_feature_is_rejected() {
+    assert!(
+        "hello=time_travel"
+            .parse::<ExperimentalFeatureOverride>()
+            .is_err()
+    );
+    assert!("coupons".parse::<ExperimentalFeatureOverride>().is_err());
+}
+
+#[test]
+fn unknown_features_and_unmatched_overrides_are_returned() {
+    let manifest_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/experimental_features/Scarb.toml");
+    let unmatched: ExperimentalFeatureOverride = "missing_crate=coupons".parse().unwrap();
+    let options = LoadOptions {
+        experimental_feature_overrides: vec![
+            "with_features=coupons".parse().unwrap(),
+            unmatched.clone(),
+        ],
+        ..Default::default()
+    };
+
+    let project = load_scarb_project_with_options(manifest_path, &options).unwrap();
+
+    assert_eq!(
+        project.unknown_experimental_features,
+        vec![UnknownExperimentalFeature {
+            package_name: "with_features".into(),
+            feature: "time_travel".into(),
+        }]
+    );
+    assert_eq!(
+        project.unmatched_experimental_feature_overrides,
+        vec![unmatched]
+    );
+
+    let cr = project
+        .crates
+        .iter()
+        .find(|cr| cr.name == "with_features")
+        .unwrap();
+    assert!(cr.settings.experimental_features.negative_impls);
+    assert!(cr.settings.experimental_features.coupons);
+}
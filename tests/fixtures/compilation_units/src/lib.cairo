@@ -0,0 +1,8 @@
+pub fn answer() -> felt252 {
+    42
+}
+
+#[cfg(test)]
+fn only_in_tests() -> felt252 {
+    missing_in_tests
+}
@@ -0,0 +1,3 @@
+fn a() -> felt252 {
+    undefined_in_a
+}
@@ -0,0 +1,3 @@
+fn b() -> felt252 {
+    undefined_in_b
+}
@@ -0,0 +1,3 @@
+fn lib() -> felt252 {
+    undefined_in_lib
+}
@@ -0,0 +1,5 @@
+mod utils;
+
+fn main() -> felt252 {
+    utils::double(21)
+}
@@ -0,0 +1,3 @@
+pub fn double(x: felt252) -> felt252 {
+    x + x
+}
@@ -0,0 +1,3 @@
+fn main() -> u32 {
+    b::answer()
+}
@@ -0,0 +1,4 @@
+fn broken() -> felt252 {
+    let x = 5
+    x
+}
@@ -0,0 +1,10 @@
+mod broken;
+
+fn main() -> u32 {
+    helpers::answer()
+}
+
+fn unreachable() -> felt252 {
+    return 1;
+    2
+}
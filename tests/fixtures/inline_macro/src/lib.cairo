@@ -0,0 +1,9 @@
+// The expansion of this macro is not a valid expression.
+// The resulting parser error lives only in the virtual file generated for the expansion.
+macro broken_add {
+    ($x:expr) => { $x + };
+}
+
+fn main() -> felt252 {
+    broken_add!(1)
+}
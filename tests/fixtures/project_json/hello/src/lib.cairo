@@ -0,0 +1,3 @@
+fn main() -> felt252 {
+    world::answer()
+}
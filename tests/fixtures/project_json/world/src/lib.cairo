@@ -0,0 +1,3 @@
+pub fn answer() -> felt252 {
+    42
+}
@@ -0,0 +1,3 @@
+pub fn first() -> u32 {
+    1
+}
@@ -0,0 +1,3 @@
+pub fn second() -> u32 {
+    2
+}
@@ -0,0 +1,55 @@
+use demo_ls::{LoadOptions, WorkspaceShape, generate_workspace, load_scarb_project_with_options};
+use std::fs;
+use std::process;
+
+#[test]
+fn generated_workspace_has_requested_crates_and_dependencies() {
+    let path = std::env::temp_dir().join(format!("demo-ls-generate-{}", process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let shape = WorkspaceShape {
+        crates: 5,
+        modules_per_crate: 2,
+        module_depth: 2,
+        dependencies_per_crate: 2,
+        generics: true,
+        starknet_contracts: false,
+        errors: 0,
+    };
+
+    generate_workspace(&path, &shape).unwrap();
+    let project = load_scarb_project_with_options(path.join("Scarb.toml"), &LoadOptions::default());
+    fs::remove_dir_all(&path).unwrap();
+    let project = project.unwrap();
+
+    let mut members: Vec<_> = project
+        .crates
+        .iter()
+        .filter(|cr| cr.is_workspace_member)
+        .collect();
+    members.sort_by_key(|cr| cr.name.clone());
+    members.dedup_by_key(|cr| cr.name.clone());
+    assert_eq!(
+        members
+            .iter()
+            .map(|cr| cr.name.as_str())
+            .collect::<Vec<_>>(),
+        ["crate_0", "crate_1", "crate_2", "crate_3", "crate_4"]
+    );
+
+    for (index, cr) in members.iter().enumerate() {
+        let mut dependencies: Vec<_> = cr
+            .settings
+            .dependencies
+            .keys()
+            .filter(|name| name.starts_with("crate_") && name.as_str() != cr.name.as_str())
+            .cloned()
+            .collect();
+        dependencies.sort();
+
+        let expected: Vec<_> = (index + 1..shape.crates)
+            .take(shape.dependencies_per_crate)
+            .map(|dependency| format!("crate_{dependency}"))
+            .collect();
+        assert_eq!(dependencies, expected, "dependencies of {}", cr.name);
+    }
+}
@@ -0,0 +1,92 @@
+//! Golden-file tests for the diagnostics pipeline.
+//!
+//! Every directory in `tests/fixtures/golden` is a project described either by a `Scarb.toml` or
+//! a `cairo_project.toml`. The project is loaded, diagnostics are calculated for all its files and
+//! compared with the `diagnostics.expected` file checked in next to the project manifest.
+//!
+//! A fixture without an expected file fails the test. Run with `DEMO_LS_BLESS=1` to create or
+//! overwrite expected files with the actual output.
+
+use demo_ls::{calculate_diagnostics_for_all_files, load_cairo_project, load_scarb_project};
+use std::fs;
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes the harness write actual output to `.expected` files.
+const BLESS_ENV: &str = "DEMO_LS_BLESS";
+
+const EXPECTED_FILE_NAME: &str = "diagnostics.expected";
+
+/// Placeholder replacing the absolute path of a fixture in diagnostics locations.
+const FIXTURE_ROOT_PLACEHOLDER: &str = "[FIXTURE_ROOT]";
+
+#[test]
+fn golden_diagnostics() {
+    let bless = std::env::var_os(BLESS_ENV).is_some_and(|value| value != "0");
+
+    let mut failures = vec![];
+    for fixture in fixtures() {
+        let actual = diagnostics_for_fixture(&fixture);
+        let expected_path = fixture.join(EXPECTED_FILE_NAME);
+
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+
+        // A missing expected file must not pass as "no diagnostics", as losing diagnostics is
+        // exactly what this harness is supposed to catch.
+        let expected = match fs::read_to_string(&expected_path) {
+            Ok(expected) => expected,
+            Err(e) => {
+                failures.push(format!("failed to read {}: {e}", expected_path.display()));
+                continue;
+            }
+        };
+        if actual != expected {
+            failures.push(format!(
+                "diagnostics of fixture {fixture} differ from {EXPECTED_FILE_NAME}\n\
+                 --- expected\n{expected}\n--- actual\n{actual}",
+                fixture = fixture.display(),
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nrun with `{BLESS_ENV}=1` to update expected files",
+        failures.join("\n\n")
+    );
+}
+
+/// Returns paths of all fixture projects, sorted to make failures reproducible.
+fn fixtures() -> Vec<PathBuf> {
+    let fixtures_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+
+    let mut fixtures: Vec<_> = fs::read_dir(fixtures_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    fixtures.sort();
+
+    fixtures
+}
+
+/// Loads the fixture project and returns its diagnostics in a stable, machine-independent form.
+fn diagnostics_for_fixture(fixture: &Path) -> String {
+    let scarb_manifest = fixture.join("Scarb.toml");
+    let db = if scarb_manifest.exists() {
+        load_scarb_project(scarb_manifest)
+    } else {
+        load_cairo_project(fixture)
+    }
+    .unwrap_or_else(|e| panic!("failed to load fixture {}: {e:?}", fixture.display()));
+
+    let diagnostics = calculate_diagnostics_for_all_files(&db, NonZero::new(4).unwrap());
+
+    let fixture_root = fixture.to_str().unwrap();
+    diagnostics
+        .concat()
+        .replace(fixture_root, FIXTURE_ROOT_PLACEHOLDER)
+}
@@ -0,0 +1,19 @@
+use demo_ls::{calculate_diagnostics_for_all_files, load_cairo_project};
+use std::num::NonZero;
+use std::path::Path;
+
+#[test]
+fn diagnostics_from_inline_macro_expansion_are_collected() {
+    let project_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/inline_macro");
+    let db = load_cairo_project(&project_path).unwrap();
+
+    let diagnostics = calculate_diagnostics_for_all_files(&db, NonZero::new(1).unwrap());
+
+    // Syntax diagnostics of the expansion file are only reported when the file is processed.
+    assert!(
+        diagnostics
+            .iter()
+            .any(|entry| entry.starts_with("error: Missing tokens. Expected an expression.")),
+        "syntax diagnostic from the inline macro expansion is missing: {diagnostics:#?}"
+    );
+}
@@ -0,0 +1,95 @@
+use cairo_lang_filesystem::db::Edition;
+use demo_ls::{BuiltinPlugin, LoadOptions, ProjectJson, load_project_json};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/project_json")
+        .join(name)
+}
+
+#[test]
+fn format_round_trips() {
+    let content = fs::read_to_string(fixture("project.json")).unwrap();
+    let project: ProjectJson = serde_json::from_str(&content).unwrap();
+
+    let serialized = serde_json::to_string(&project).unwrap();
+    let deserialized: ProjectJson = serde_json::from_str(&serialized).unwrap();
+
+    assert_eq!(deserialized.crates, project.crates);
+}
+
+#[test]
+fn crates_are_loaded_with_paths_relative_to_the_file() {
+    let project = load_project_json(&fixture("project.json"), &LoadOptions::default()).unwrap();
+    let base_dir = fixture("").canonicalize().unwrap();
+
+    let [hello, world] = project.crates.as_slice() else {
+        panic!("expected two crates, got: {:#?}", project.crates);
+    };
+
+    assert_eq!(hello.name, "hello");
+    assert_eq!(hello.root, base_dir.join("hello/src"));
+    assert_eq!(hello.settings.edition, Edition::V2024_07);
+    assert!(hello.settings.experimental_features.coupons);
+    assert_eq!(
+        hello.settings.dependencies["world"]
+            .discriminator
+            .as_deref(),
+        Some("world 1.0.0")
+    );
+    assert!(hello.builtin_plugins.contains(&BuiltinPlugin::CairoTest));
+    assert!(hello.is_workspace_member);
+
+    assert_eq!(world.name, "world");
+    assert_eq!(world.root, base_dir.join("world/src"));
+    assert_eq!(world.settings.edition, Edition::V2023_10);
+    assert_eq!(world.settings.cfg_set, None);
+    assert!(!world.is_workspace_member);
+    assert!(world.is_immutable);
+}
+
+#[test]
+fn malformed_file_is_rejected() {
+    let path = fixture("malformed.json");
+
+    let error = load_project_json(&path, &LoadOptions::default())
+        .err()
+        .expect("loading a malformed file should fail");
+
+    assert_eq!(
+        error.to_string(),
+        format!("failed to parse project JSON: {}", path.display())
+    );
+    assert!(
+        format!("{error:?}").contains("missing field `root`"),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn crate_without_discriminator_is_rejected() {
+    let error = load_project_json(
+        &fixture("missing_discriminator.json"),
+        &LoadOptions::default(),
+    )
+    .err()
+    .expect("loading a crate without a discriminator should fail");
+
+    assert!(error.to_string().contains("found crate hello"), "{error:?}");
+}
+
+#[test]
+fn missing_file_is_rejected() {
+    let path = fixture("nonexistent.json");
+
+    let error = load_project_json(&path, &LoadOptions::default())
+        .err()
+        .expect("loading a missing file should fail");
+
+    assert_eq!(
+        error.to_string(),
+        format!("failed to read project JSON: {}", path.display())
+    );
+}
@@ -0,0 +1,51 @@
+use demo_ls::{LoadOptions, calculate_diagnostics_for_all_files, compare_warm_up, load_project};
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+
+fn project_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden/dependent_crates")
+}
+
+#[test]
+fn warm_up_is_sampled_with_unchanged_diagnostics() {
+    let paths = [project_path()];
+    let threads_limit = NonZero::new(2).unwrap();
+
+    let (diagnostics, report) = compare_warm_up(
+        &paths,
+        &LoadOptions::default(),
+        threads_limit,
+        &["core".to_string()],
+        NonZero::new(3).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(report.crates, ["core"]);
+    assert_eq!(report.warm_up.len(), 3);
+    assert_eq!(report.with_warm_up.len(), 3);
+    assert_eq!(report.without_warm_up.len(), 3);
+    assert!(report.warm_up.median() <= report.with_warm_up.median());
+
+    let db = load_project(&paths, &LoadOptions::default()).unwrap().db;
+    assert_eq!(
+        diagnostics,
+        calculate_diagnostics_for_all_files(&db, threads_limit)
+    );
+}
+
+#[test]
+fn unknown_warm_up_crate_is_rejected() {
+    let error = compare_warm_up(
+        &[project_path()],
+        &LoadOptions::default(),
+        NonZero::new(2).unwrap(),
+        &["missing_crate".to_string()],
+        NonZero::new(1).unwrap(),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "warm-up crate not found in the project: missing_crate"
+    );
+}